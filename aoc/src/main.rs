@@ -0,0 +1,556 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::{env, io};
+
+// Random puzzle inputs in each day's format, for fuzzing, benchmarks and property tests.
+// The same day, seed and size always give the same input.
+//
+//     cargo run -- gen <day> [--seed N] [--size N]
+
+// The days there's a generator for, and the size each uses by default (roughly that of a real
+// input), along with what the size counts.
+const DAYS: [(u32, usize, &str); 12] = [
+    (1, 1000, "lines"),
+    (2, 100, "games"),
+    (3, 140, "rows and columns"),
+    (4, 200, "cards"),
+    (5, 30, "lookups per map"),
+    (6, 4, "races"),
+    (7, 1000, "hands"),
+    (8, 280, "instructions"),
+    (9, 200, "sequences"),
+    (10, 140, "rows and columns"),
+    (11, 140, "rows and columns"),
+    (16, 110, "rows and columns"),
+];
+
+const DIGIT_NAMES: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+
+fn generate(day: u32, seed: u64, size: usize) -> Option<String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let rng = &mut rng;
+    let input = match day {
+        1 => calibration_lines(rng, size),
+        2 => cube_games(rng, size),
+        3 => schematic(rng, size),
+        4 => scratchcards(rng, size),
+        5 => almanac(rng, size),
+        6 => races(rng, size),
+        7 => camel_cards(rng, size),
+        8 => network(rng, size),
+        9 => sequences(rng, size),
+        10 => pipe_map(rng, size),
+        11 => galaxy_image(rng, size),
+        16 => contraption(rng, size),
+        _ => return None,
+    };
+    Some(input)
+}
+
+// Letters with digits and spelled-out digits mixed in.  Every line has at least one numeral,
+// so part 1 works on all of them.
+fn calibration_lines(rng: &mut StdRng, size: usize) -> String {
+    let lines: Vec<String> = (0..size)
+        .map(|_| {
+            let mut line = String::new();
+            let numeral_at = rng.gen_range(0..4);
+            for i in 0..rng.gen_range(4..10) {
+                match rng.gen_range(0..4) {
+                    _ if i == numeral_at => line.push(char::from(b'0' + rng.gen_range(1..10))),
+                    0 => line.push_str(DIGIT_NAMES[rng.gen_range(1..10)]),
+                    1 => line.push(char::from(b'0' + rng.gen_range(1..10))),
+                    _ => line.push(char::from(rng.gen_range(b'a'..=b'z'))),
+                }
+            }
+            line
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+fn cube_games(rng: &mut StdRng, size: usize) -> String {
+    let lines: Vec<String> = (1..=size)
+        .map(|id| {
+            let rounds: Vec<String> = (0..rng.gen_range(1..=6))
+                .map(|_| {
+                    let mut colours = vec!["red", "green", "blue"];
+                    colours.shuffle(rng);
+                    colours.truncate(rng.gen_range(1..=3));
+                    let draws: Vec<String> = colours.iter().map(|c| format!("{} {}", rng.gen_range(1..=20), c)).collect();
+                    draws.join(", ")
+                })
+                .collect();
+            format!("Game {}: {}", id, rounds.join("; "))
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+// Numbers of up to three digits and symbols scattered over dots.  Numbers on the same row
+// always have something between them.
+fn schematic(rng: &mut StdRng, size: usize) -> String {
+    let symbols = b"*#+$/@=%&-";
+    let lines: Vec<String> = (0..size)
+        .map(|_| {
+            let mut row = String::new();
+            while row.len() < size {
+                let left = size - row.len();
+                match rng.gen_range(0..10) {
+                    0 | 1 if left >= 4 => {
+                        let digits = rng.gen_range(1..=3);
+                        row += &format!("{}.", rng.gen_range(10u32.pow(digits - 1)..10u32.pow(digits)));
+                    }
+                    2 => row.push(char::from(*symbols.choose(rng).unwrap())),
+                    _ => row.push('.'),
+                }
+            }
+            row
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+// Ten winning numbers and twenty-five of ours, laid out as in the real input.  No card wins
+// copies of cards past the end of the table, and once a card has a hundred thousand copies it
+// wins nothing, so the number of cards stays in the millions as it does for real inputs.
+fn scratchcards(rng: &mut StdRng, size: usize) -> String {
+    let width = size.to_string().len().max(3);
+    let mut copies = vec![1u64; size + 1];
+    let lines: Vec<String> = (1..=size)
+        .map(|id| {
+            let mut numbers: Vec<u32> = (1..100).collect();
+            numbers.shuffle(rng);
+            let winners = &numbers[..10];
+            let matches = if copies[id] > 100_000 { 0 } else { rng.gen_range(0..=10.min(size - id)) };
+            for won in id + 1..=id + matches {
+                copies[won] += copies[id];
+            }
+            let mut mine: Vec<u32> = winners[..matches].iter().chain(&numbers[10..35 - matches]).copied().collect();
+            mine.shuffle(rng);
+
+            let list = |ns: &[u32]| ns.iter().map(|n| format!("{:>2}", n)).collect::<Vec<_>>().join(" ");
+            format!("Card {:>w$}: {} | {}", id, list(winners), list(&mine), w = width)
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+// Ten seed ranges, then seven maps.  Each map cuts the numbers below 2^32 into pieces and lays
+// them back down end to end in a random order, so every number maps to exactly one other.
+// Pieces that end up where they started are left out.
+fn almanac(rng: &mut StdRng, size: usize) -> String {
+    const LIMIT: u64 = 1 << 32;
+    let mut seeds = vec![];
+    for _ in 0..10 {
+        let start = rng.gen_range(0..LIMIT - 1);
+        seeds.push(format!("{} {}", start, rng.gen_range(1..=(LIMIT - start).min(1 << 28))));
+    }
+    let mut sections = vec![format!("seeds: {}", seeds.join(" "))];
+
+    let names = ["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"];
+    for pair in names.windows(2) {
+        let mut cuts: Vec<u64> = (1..size.max(1)).map(|_| rng.gen_range(1..LIMIT)).collect();
+        cuts.extend([0, LIMIT]);
+        cuts.sort();
+        cuts.dedup();
+        let mut pieces: Vec<(u64, u64)> = cuts.windows(2).map(|w| (w[0], w[1] - w[0])).collect();
+        pieces.shuffle(rng);
+
+        let mut lines = vec![format!("{}-to-{} map:", pair[0], pair[1])];
+        let mut destination = 0;
+        for (source, length) in pieces {
+            if source != destination {
+                lines.push(format!("{} {} {}", destination, source, length));
+            }
+            destination += length;
+        }
+        sections.push(lines.join("\n"));
+    }
+
+    sections.join("\n\n")
+}
+
+// Each race can be won, and the record is a real distance for some button press.
+fn races(rng: &mut StdRng, size: usize) -> String {
+    let (times, distances): (Vec<u64>, Vec<u64>) = (0..size)
+        .map(|_| {
+            let time = rng.gen_range(7..100);
+            let press = rng.gen_range(1..time / 2);
+            (time, press * (time - press))
+        })
+        .unzip();
+    let row = |ns: &[u64]| ns.iter().map(|n| format!("{:>6}", n)).collect::<String>();
+
+    format!("Time:    {}\nDistance:{}", row(&times), row(&distances))
+}
+
+// Distinct hands with bids.
+fn camel_cards(rng: &mut StdRng, size: usize) -> String {
+    let cards = b"23456789TJQKA";
+    let mut seen = HashSet::new();
+    let mut lines = vec![];
+    while lines.len() < size.min(13usize.pow(5)) {
+        let hand: String = (0..5).map(|_| char::from(*cards.choose(rng).unwrap())).collect();
+        if seen.insert(hand.clone()) {
+            lines.push(format!("{} {}", hand, rng.gen_range(1..=1000)));
+        }
+    }
+
+    lines.join("\n")
+}
+
+// Six ghosts, each going round its own ring of nodes whose length is a distinct prime, from its
+// **A node to its **Z node and back round.  Each step round a ring is between a pair of twin
+// nodes, so left and right lead to different nodes but the same distance round.  AAA leads to
+// ZZZ.
+fn network(rng: &mut StdRng, size: usize) -> String {
+    let instructions: String = (0..size.max(1)).map(|_| if rng.gen() { 'L' } else { 'R' }).collect();
+
+    let mut primes = vec![41, 43, 47, 53, 59, 61, 67, 71, 73, 79];
+    primes.shuffle(rng);
+
+    // Intermediate nodes can't end in A or Z.
+    let mut used: HashSet<String> = HashSet::from(["AAA".to_string(), "ZZZ".to_string()]);
+    let mut name = |rng: &mut StdRng, last: Option<char>| loop {
+        let mut name: String = (0..2).map(|_| char::from(rng.gen_range(b'A'..=b'Z'))).collect();
+        name.push(last.unwrap_or_else(|| char::from(rng.gen_range(b'B'..=b'Y'))));
+        if used.insert(name.clone()) {
+            return name;
+        }
+    };
+
+    let mut nodes = vec![];
+    for (ghost, length) in primes[..6].iter().enumerate() {
+        let (start, end) = match ghost {
+            0 => ("AAA".to_string(), "ZZZ".to_string()),
+            _ => (name(rng, Some('A')), name(rng, Some('Z'))),
+        };
+        let twins: Vec<(String, String)> = (1..*length).map(|_| (name(rng, None), name(rng, None))).collect();
+        let first = &twins[0];
+        nodes.push(format!("{} = ({}, {})", start, first.0, first.1));
+        nodes.push(format!("{} = ({}, {})", end, first.0, first.1));
+        for (i, (left, right)) in twins.iter().enumerate() {
+            let next = twins.get(i + 1).map_or((end.clone(), end.clone()), |t| t.clone());
+            nodes.push(format!("{} = ({}, {})", left, next.0, next.1));
+            nodes.push(format!("{} = ({}, {})", right, next.0, next.1));
+        }
+    }
+    nodes.shuffle(rng);
+
+    format!("{}\n\n{}", instructions, nodes.join("\n"))
+}
+
+// Twenty-one values of a polynomial of degree up to five.
+fn sequences(rng: &mut StdRng, size: usize) -> String {
+    let lines: Vec<String> = (0..size)
+        .map(|_| {
+            let coefficients: Vec<i64> = (0..=rng.gen_range(0..=5)).map(|_| rng.gen_range(-9..=9)).collect();
+            let values: Vec<String> = (0..21i64)
+                .map(|x| coefficients.iter().rev().fold(0, |value, c| value * x + c).to_string())
+                .collect();
+            values.join(" ")
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+// A loop traced round the edge of a random blob, with stray pipes everywhere else.  The blob is
+// a run of columns of blocks across most of the map, each column overlapping the last, so its
+// edge never touches itself.  The corners of the blocks are the tiles, so a blob of
+// (size - 1) x (size - 1) blocks gives a size x size map.
+fn pipe_map(rng: &mut StdRng, size: usize) -> String {
+    let size = size.max(3);
+    let blocks = size - 1;
+    let first = rng.gen_range(0..=blocks / 4);
+    let last = rng.gen_range(blocks * 3 / 4..blocks).max(first);
+    let mut columns = vec![None; blocks];
+    let (mut top, mut bottom) = (rng.gen_range(0..=blocks / 4), rng.gen_range(blocks * 3 / 4..blocks));
+    for column in columns.iter_mut().take(last + 1).skip(first) {
+        let new_top = rng.gen_range(top.saturating_sub(3)..=bottom);
+        let new_bottom = rng.gen_range(top.max(new_top)..=(bottom + 3).min(blocks - 1));
+        (top, bottom) = (new_top, new_bottom);
+        *column = Some((top, bottom));
+    }
+    let inside = |r: isize, c: isize| {
+        r >= 0 && c >= 0 && (c as usize) < blocks && columns[c as usize].is_some_and(|(t, b)| t as isize <= r && r <= b as isize)
+    };
+
+    // Corner (r, c) joins the corner north of it if exactly one of the blocks either side of
+    // that edge is in the blob, and likewise for the other directions.
+    let mut grid: Vec<Vec<char>> = vec![vec!['.'; size]; size];
+    let mut on_loop = vec![];
+    for (r, row) in grid.iter_mut().enumerate() {
+        for (c, tile) in row.iter_mut().enumerate() {
+            let (r, c) = (r as isize, c as isize);
+            let north = inside(r - 1, c - 1) != inside(r - 1, c);
+            let south = inside(r, c - 1) != inside(r, c);
+            let west = inside(r - 1, c - 1) != inside(r, c - 1);
+            let east = inside(r - 1, c) != inside(r, c);
+            *tile = match (north, east, south, west) {
+                (true, false, true, false) => '|',
+                (false, true, false, true) => '-',
+                (true, true, false, false) => 'L',
+                (true, false, false, true) => 'J',
+                (false, false, true, true) => '7',
+                (false, true, true, false) => 'F',
+                _ => *b"|-LJ7F...".choose(rng).unwrap() as char,
+            };
+            if north || east || south || west {
+                on_loop.push((r as usize, c as usize));
+            }
+        }
+    }
+
+    // S replaces a loop tile, and nothing off the loop next to it points at it, so there's
+    // only one way to fill it back in.
+    let (sr, sc) = *on_loop.choose(rng).unwrap();
+    let pipe = grid[sr][sc];
+    grid[sr][sc] = 'S';
+    let neighbours = [
+        (sr.wrapping_sub(1), sc, "|LJ", "|7F"),
+        (sr + 1, sc, "|7F", "|LJ"),
+        (sr, sc.wrapping_sub(1), "-J7", "-LF"),
+        (sr, sc + 1, "-LF", "-J7"),
+    ];
+    for (r, c, s_exits, joins_s) in neighbours {
+        if r < size && c < size && !s_exits.contains(pipe) && joins_s.contains(grid[r][c]) {
+            grid[r][c] = '.';
+        }
+    }
+
+    let lines: Vec<String> = grid.into_iter().map(|row| row.into_iter().collect()).collect();
+    lines.join("\n")
+}
+
+// Galaxies about one tile in fifty, with some rows and columns left empty.
+fn galaxy_image(rng: &mut StdRng, size: usize) -> String {
+    let empty_rows: HashSet<usize> = (0..size).filter(|_| rng.gen_ratio(1, 15)).collect();
+    let empty_cols: HashSet<usize> = (0..size).filter(|_| rng.gen_ratio(1, 15)).collect();
+    let lines: Vec<String> = (0..size)
+        .map(|r| {
+            (0..size)
+                .map(|c| {
+                    let empty = empty_rows.contains(&r) || empty_cols.contains(&c);
+                    if !empty && rng.gen_ratio(1, 50) { '#' } else { '.' }
+                })
+                .collect()
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+// Mirrors and splitters about one tile in ten.
+fn contraption(rng: &mut StdRng, size: usize) -> String {
+    let lines: Vec<String> = (0..size)
+        .map(|_| {
+            (0..size)
+                .map(|_| if rng.gen_ratio(1, 10) { *b"/\\|-".choose(rng).unwrap() as char } else { '.' })
+                .collect()
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+fn aoc() -> io::Result<()> {
+    let usage = || {
+        let days: Vec<String> = DAYS.iter().map(|(day, size, what)| format!("  day {:>2}: size is {} (default {})", day, what, size)).collect();
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("usage: aoc gen <day> [--seed N] [--size N]\n{}", days.join("\n")),
+        )
+    };
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    let ["gen", day, ref options @ ..] = args[..] else {
+        return Err(usage());
+    };
+    let day: u32 = day.parse().map_err(|_| usage())?;
+    let &(_, default_size, _) = DAYS.iter().find(|(d, _, _)| *d == day).ok_or_else(usage)?;
+
+    let (mut seed, mut size) = (0, default_size);
+    for option in options.chunks(2) {
+        match option {
+            ["--seed", n] => seed = n.parse().map_err(|_| usage())?,
+            ["--size", n] => size = n.parse().map_err(|_| usage())?,
+            _ => return Err(usage()),
+        }
+    }
+
+    println!("{}", generate(day, seed, size).ok_or_else(usage)?);
+
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    aoc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn seeded() {
+        for (day, _, _) in DAYS {
+            let input = generate(day, 7, 20).unwrap();
+            assert_eq!(generate(day, 7, 20).unwrap(), input, "day {}", day);
+            assert_ne!(generate(day, 8, 20).unwrap(), input, "day {}", day);
+        }
+        assert_eq!(generate(12, 0, 20), None);
+    }
+
+    #[test]
+    fn line_formats() {
+        for seed in 0..20 {
+            for line in generate(1, seed, 50).unwrap().lines() {
+                assert!(line.chars().any(|c| c.is_ascii_digit()), "{}", line);
+            }
+            assert_eq!(generate(3, seed, 30).unwrap().lines().map(|l| l.len()).collect::<HashSet<_>>(), HashSet::from([30]));
+            assert!(!generate(3, seed, 30).unwrap().contains(|c: char| !(c.is_ascii_digit() || "*#+$/@=%&-.\n".contains(c))));
+
+            // Cards only win copies of cards in the table.
+            let cards = generate(4, seed, 30).unwrap();
+            for (i, line) in cards.lines().enumerate() {
+                let (winners, mine) = line.split_once(": ").unwrap().1.split_once(" | ").unwrap();
+                let winners: HashSet<&str> = winners.split_whitespace().collect();
+                let mine: Vec<&str> = mine.split_whitespace().collect();
+                assert_eq!((winners.len(), mine.len()), (10, 25));
+                assert!(i + mine.iter().filter(|n| winners.contains(*n)).count() < 30);
+            }
+        }
+    }
+
+    #[test]
+    fn almanac_maps_cover_once() {
+        for seed in 0..10 {
+            let input = generate(5, seed, 8).unwrap();
+            let sections: Vec<&str> = input.split("\n\n").collect();
+            assert_eq!(sections.len(), 8);
+            assert_eq!(sections[0].split_whitespace().count(), 21);
+            for section in &sections[1..] {
+                let lookups: Vec<Vec<u64>> = section
+                    .lines()
+                    .skip(1)
+                    .map(|l| l.split_whitespace().map(|n| n.parse().unwrap()).collect())
+                    .collect();
+                assert!(!lookups.is_empty() && lookups.len() <= 8);
+                let mut sources: Vec<(u64, u64)> = lookups.iter().map(|l| (l[1], l[2])).collect();
+                let mut destinations: Vec<(u64, u64)> = lookups.iter().map(|l| (l[0], l[2])).collect();
+                // Sources don't overlap, and together the destinations cover the same numbers.
+                sources.sort();
+                destinations.sort();
+                assert!(sources.windows(2).all(|w| w[0].0 + w[0].1 <= w[1].0));
+                assert!(destinations.windows(2).all(|w| w[0].0 + w[0].1 <= w[1].0));
+                let total = |pieces: &[(u64, u64)]| pieces.iter().map(|p| p.1).sum::<u64>();
+                assert_eq!(total(&sources), total(&destinations));
+            }
+        }
+    }
+
+    #[test]
+    fn network_cycles() {
+        let input = generate(8, 3, 10).unwrap();
+        let (instructions, nodes) = input.split_once("\n\n").unwrap();
+        let network: HashMap<&str, (&str, &str)> =
+            nodes.lines().map(|l| (&l[0..3], (&l[7..10], &l[12..15]))).collect();
+        assert_eq!(network.len(), nodes.lines().count());
+
+        let starts: Vec<&str> = network.keys().filter(|n| n.ends_with('A')).copied().collect();
+        assert_eq!(starts.len(), 6);
+        assert!(starts.contains(&"AAA"));
+        for start in starts {
+            // Every ghost reaches a Z after the same number of steps each time round.
+            let mut node = start;
+            let mut hits = vec![];
+            for (step, instruction) in instructions.chars().cycle().enumerate().take(500) {
+                node = if instruction == 'L' { network[node].0 } else { network[node].1 };
+                if node.ends_with('Z') {
+                    hits.push((step + 1, node));
+                }
+            }
+            assert!(hits.len() >= 2, "{}", start);
+            assert!(hits.iter().all(|(step, _)| step % hits[0].0 == 0));
+            if start == "AAA" {
+                assert!(hits.iter().all(|(_, node)| *node == "ZZZ"));
+            }
+        }
+    }
+
+    #[test]
+    fn sequences_are_polynomials() {
+        // Differencing reaches all zeroes before running out of values.
+        for line in generate(9, 1, 50).unwrap().lines() {
+            let mut values: Vec<i64> = line.split_whitespace().map(|v| v.parse().unwrap()).collect();
+            assert_eq!(values.len(), 21);
+            for _ in 0..6 {
+                values = values.windows(2).map(|w| w[1] - w[0]).collect();
+            }
+            assert!(values.iter().all(|v| *v == 0));
+        }
+    }
+
+    #[test]
+    fn pipe_loop_closes() {
+        for seed in 0..30 {
+            let input = generate(10, seed, 25).unwrap();
+            let grid: Vec<Vec<char>> = input.lines().map(|l| l.chars().collect()).collect();
+            assert_eq!((grid.len(), grid[0].len()), (25, 25));
+            let (sr, sc) = (0..25).flat_map(|r| (0..25).map(move |c| (r, c))).find(|(r, c)| grid[*r][*c] == 'S').unwrap();
+
+            // Exactly two pipes point at S, and following one round comes back to S.
+            let exits = |ch: char| -> Vec<(isize, isize)> {
+                match ch {
+                    '|' => vec![(-1, 0), (1, 0)],
+                    '-' => vec![(0, -1), (0, 1)],
+                    'L' => vec![(-1, 0), (0, 1)],
+                    'J' => vec![(-1, 0), (0, -1)],
+                    '7' => vec![(1, 0), (0, -1)],
+                    'F' => vec![(1, 0), (0, 1)],
+                    _ => vec![],
+                }
+            };
+            let tile = |r: isize, c: isize| if r < 0 || c < 0 || r >= 25 || c >= 25 { '.' } else { grid[r as usize][c as usize] };
+            let (sr, sc) = (sr as isize, sc as isize);
+            let joining: Vec<(isize, isize)> = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .into_iter()
+                .filter(|(dr, dc)| exits(tile(sr + dr, sc + dc)).contains(&(-dr, -dc)))
+                .collect();
+            assert_eq!(joining.len(), 2, "seed {}\n{}", seed, input);
+
+            let (mut previous, mut current) = ((sr, sc), (sr + joining[0].0, sc + joining[0].1));
+            let mut length = 1;
+            while current != (sr, sc) {
+                let next = exits(tile(current.0, current.1))
+                    .into_iter()
+                    .map(|(dr, dc)| (current.0 + dr, current.1 + dc))
+                    .find(|n| *n != previous)
+                    .unwrap();
+                (previous, current) = (current, next);
+                length += 1;
+                assert!(length <= 25 * 25);
+            }
+            assert_eq!(length % 2, 0);
+        }
+    }
+
+    #[test]
+    fn grids() {
+        for day in [11, 16] {
+            let input = generate(day, 5, 40).unwrap();
+            assert_eq!(input.lines().count(), 40);
+            assert!(input.lines().all(|l| l.len() == 40));
+        }
+        assert!(generate(11, 5, 40).unwrap().contains('#'));
+    }
+}
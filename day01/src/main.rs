@@ -1,56 +1,455 @@
-use regex::Regex;
-use std::fs::File;
-use std::io::{self, prelude::*, BufReader};
+use aho_corasick::{AhoCorasick, AhoCorasickKind};
+use std::{env, fmt, fs, io};
 
-fn as_digit(v : &str) -> u32 {
-    match v {
-        "zero" | "0" => 0,
-        "one" | "1" => 1,
-        "two" | "2" => 2,
-        "three" | "3" => 3,
-        "four" | "4" => 4,
-        "five" | "5" => 5,
-        "six" | "6" => 6,
-        "seven" | "7" => 7,
-        "eight" | "8" => 8,
-        "nine" | "9" => 9,
-        _ => unimplemented!()
+const ENGLISH_NAMES: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+
+// The words (or numerals) that count as digits, and the value of each.
+#[derive(Debug, Clone)]
+struct Vocabulary {
+    words: Vec<(String, u32)>,
+}
+
+impl Vocabulary {
+    // Just the numerals, for part 1.
+    fn numerals() -> Self {
+        Self { words: (0..10).map(|d| (d.to_string(), d)).collect() }
     }
+
+    // The numerals and their English names, for part 2.
+    fn english() -> Self {
+        let mut vocabulary = Self::numerals();
+        vocabulary.add(ENGLISH_NAMES.iter().zip(0..).map(|(name, d)| (name.to_string(), d)));
+        vocabulary
+    }
+
+    // One word and its value per line, e.g. "eins 1" or "eleven 11".  Values go up to 9999, so
+    // two of them written side by side still fit in a u32.
+    fn from_table(table: &str) -> Option<Self> {
+        let mut words = vec![];
+        for line in table.lines().filter(|l| !l.trim().is_empty()) {
+            let (word, value) = line.trim().rsplit_once(' ')?;
+            words.push((word.trim().to_string(), value.parse().ok().filter(|v| *v <= 9999)?));
+        }
+        Some(Self { words })
+    }
+
+    fn add(&mut self, words: impl IntoIterator<Item = (String, u32)>) {
+        self.words.extend(words);
+    }
+}
+
+// The first and last values written side by side, as the calibration value: 17 and 3 make 173.
+// For single digits that's ten times the first plus the last.
+fn join_digits(first: u32, last: u32) -> u32 {
+    first * 10u32.pow(last.checked_ilog10().unwrap_or(0) + 1) + last
 }
 
-fn sum_calibration_values() {
-    let mut part1_total: u32 = 0;
-    let mut part2_total: u32 = 0;
+// A word from the Vocabulary found in a line: where it starts and ends, in bytes, and its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Token {
+    start: usize,
+    end: usize,
+    value: u32,
+}
 
-    let re_p1 = Regex::new(r"[0-9]").unwrap();
-    let re = Regex::new(r"(zero|one|two|three|four|five|six|seven|eight|nine|[0-9])").unwrap();
-    let rev_re = Regex::new(r"(orez|eno|owt|eerht|ruof|evif|xis|neves|thgie|enin|[0-9])").unwrap();
+// Every token in a line, overlapping ones included, in order of where they start (longest
+// first), and which of them were picked as the first and last digit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineScan {
+    tokens: Vec<Token>,
+    first: Option<usize>,
+    last: Option<usize>,
+}
 
-    let file = File::open("input.txt")
-        .unwrap_or_else(|_| panic!("File 'input.txt' not readable.") );
+impl LineScan {
+    // The first and last digits joined, or None if the line has no digits at all.
+    fn value(&self) -> Option<u32> {
+        Some(join_digits(self.tokens[self.first?].value, self.tokens[self.last?].value))
+    }
 
-    for line in BufReader::new(file).lines().filter_map(|line| line.ok()) {
-        // Part 1
-        let first_p1 = as_digit(re_p1.find(&line).unwrap().as_str());
-        let reversed_line: String = line.chars().rev().collect();
-        let last_p1 = as_digit(re_p1.find(&reversed_line).unwrap().as_str());
-        part1_total += 10 * first_p1 + last_p1;
+    // Each token as start..end=value, with the first and last in brackets.
+    fn describe(&self) -> String {
+        let tokens: Vec<String> = self
+            .tokens
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let token = format!("{}..{}={}", t.start, t.end, t.value);
+                if Some(i) == self.first || Some(i) == self.last { format!("[{}]", token) } else { token }
+            })
+            .collect();
 
-        // Part 2
-        // There's no "rfind" for regex, so just reverse everything (including the regex...)
-        let first = as_digit(re.find(&line).unwrap().as_str());
-        let reversed_line: String = line.chars().rev().collect();
-        let reversed_last: String = rev_re.find(&reversed_line).unwrap().as_str().chars().rev().collect();
-        let last = as_digit(&reversed_last);
-        part2_total += 10*first + last;
+        tokens.join(" ")
     }
+}
 
-    println!("Part 1: {}", part1_total);
-    println!("Part 2: {}", part2_total);
+// What a line calibrates to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Calibration {
+    Value(u32),
+    // Nothing from the vocabulary, but some of the reference vocabulary's words that it leaves
+    // out, like the spelled-out digits part 1 doesn't read.
+    OnlyWords,
+    NoDigits,
 }
 
-fn main() -> io::Result<()> {
-    sum_calibration_values();
+// What to do with lines that don't calibrate: leave them out, count them as 0, or stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Policy {
+    Skip,
+    Zero,
+    Error,
+}
+
+impl Policy {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "skip" => Some(Policy::Skip),
+            "zero" => Some(Policy::Zero),
+            "error" => Some(Policy::Error),
+            _ => None,
+        }
+    }
+}
+
+// A line that didn't calibrate under Policy::Error, numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    line: usize,
+    calibration: Calibration,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.calibration {
+            Calibration::OnlyWords => write!(f, "line {} only has digits this vocabulary doesn't read", self.line),
+            _ => write!(f, "line {} has no digits", self.line),
+        }
+    }
+}
+
+// The sum of the values of the lines counted, and how many lines had no digits (whether
+// they were counted as 0 or skipped).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Total {
+    sum: u64,
+    counted: usize,
+    without_digits: usize,
+}
+
+// Finds the words from a Vocabulary in each line, in a single pass over it.  Words from a
+// reference vocabulary that this one leaves out are looked for too, to tell lines with only
+// those apart from lines with no digits at all.
+struct Calibrator {
+    vocabulary: Vocabulary,
+    automaton: AhoCorasick,
+    left_out: Option<AhoCorasick>,
+}
+
+impl Calibrator {
+    fn new(vocabulary: Vocabulary) -> Self {
+        let automaton = AhoCorasick::builder()
+            .kind(Some(AhoCorasickKind::DFA))
+            .build(vocabulary.words.iter().map(|(w, _)| w))
+            .unwrap();
+        Self { vocabulary, automaton, left_out: None }
+    }
+
+    fn with_reference(mut self, reference: &Vocabulary) -> Self {
+        let left_out: Vec<&str> = reference
+            .words
+            .iter()
+            .map(|(w, _)| w.as_str())
+            .filter(|w| !self.vocabulary.words.iter().any(|(known, _)| known == w))
+            .collect();
+        self.left_out = if left_out.is_empty() { None } else { Some(AhoCorasick::new(left_out).unwrap()) };
+        self
+    }
+
+    // Overlapping matches, so both "two" and "one" are found in "twone".  The first digit is
+    // the one that starts first and the last the one that ends last, the longer winning ties,
+    // so "seventeen" beats "seven".
+    fn scan(&self, line: &str) -> LineScan {
+        let mut tokens: Vec<Token> = self
+            .automaton
+            .find_overlapping_iter(line)
+            .map(|m| Token { start: m.start(), end: m.end(), value: self.vocabulary.words[m.pattern()].1 })
+            .collect();
+        tokens.sort_by_key(|t| (t.start, std::cmp::Reverse(t.end)));
+
+        let first = if tokens.is_empty() { None } else { Some(0) };
+        let last = (0..tokens.len()).max_by_key(|&i| (tokens[i].end, tokens[i].end - tokens[i].start));
+        LineScan { tokens, first, last }
+    }
+
+    // The same choice as scan, without keeping the tokens.
+    fn value(&self, line: &str) -> Option<u32> {
+        let mut matches = self.automaton.find_overlapping_iter(line);
+        let m = matches.next()?;
+        let (mut first, mut last) = (m, m);
+        for m in matches {
+            if (m.start(), std::cmp::Reverse(m.end())) < (first.start(), std::cmp::Reverse(first.end())) {
+                first = m;
+            }
+            if (m.end(), m.len()) > (last.end(), last.len()) {
+                last = m;
+            }
+        }
+        Some(join_digits(self.vocabulary.words[first.pattern()].1, self.vocabulary.words[last.pattern()].1))
+    }
+
+    fn calibrate(&self, line: &str) -> Calibration {
+        match self.value(line) {
+            Some(value) => Calibration::Value(value),
+            None if self.left_out.as_ref().is_some_and(|left_out| left_out.is_match(line)) => Calibration::OnlyWords,
+            None => Calibration::NoDigits,
+        }
+    }
+
+    fn total<'a>(&self, lines: impl IntoIterator<Item = &'a str>, policy: Policy) -> Result<Total, Error> {
+        let mut total = Total { sum: 0, counted: 0, without_digits: 0 };
+        for (i, line) in lines.into_iter().enumerate() {
+            match (self.calibrate(line), policy) {
+                (Calibration::Value(value), _) => {
+                    total.sum += value as u64;
+                    total.counted += 1;
+                }
+                (calibration, Policy::Error) => return Err(Error { line: i + 1, calibration }),
+                (_, Policy::Zero) => {
+                    total.counted += 1;
+                    total.without_digits += 1;
+                }
+                (_, Policy::Skip) => total.without_digits += 1,
+            }
+        }
+        Ok(total)
+    }
+}
+
+fn sum_calibration_values() -> io::Result<()> {
+    // Options: `--input FILE` to use a different input, `--no-digits skip|zero|error` for what
+    // to do with lines without digits (error by default), and `--tokens` to show the part 2
+    // tokens found in each line.
+    let usage = || io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "usage: day01 [--input FILE] [--no-digits skip|zero|error] [--tokens] [VOCABULARY]",
+    );
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut input_path = "input.txt".to_string();
+    let mut policy = Policy::Error;
+    let mut show_tokens = false;
+    while let Some(option) = args.first().filter(|a| a.starts_with("--")).cloned() {
+        args.remove(0);
+        match option.as_str() {
+            "--tokens" => show_tokens = true,
+            "--input" if !args.is_empty() => input_path = args.remove(0),
+            "--no-digits" if !args.is_empty() => policy = Policy::from_name(&args.remove(0)).ok_or_else(usage)?,
+            _ => return Err(usage()),
+        }
+    }
+
+    // A trailing VOCABULARY file also totals the input with that vocabulary, laid out as in
+    // Vocabulary::from_table.
+    let custom = match args[..] {
+        [] => None,
+        [ref path] => Some(
+            Vocabulary::from_table(&fs::read_to_string(path)?)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bad vocabulary"))?,
+        ),
+        _ => return Err(usage()),
+    };
+
+    // A line only has words a calibrator doesn't read if they're English digits or from the
+    // custom vocabulary.
+    let mut reference = Vocabulary::english();
+    if let Some(custom) = &custom {
+        reference.add(custom.words.iter().cloned());
+    }
+    let part1 = Calibrator::new(Vocabulary::numerals()).with_reference(&reference);
+    let part2 = Calibrator::new(Vocabulary::english()).with_reference(&reference);
+    let custom = custom.map(|custom| Calibrator::new(custom).with_reference(&reference));
+
+    let input = fs::read_to_string(input_path)?;
+
+    if show_tokens {
+        for line in input.lines() {
+            let scan = part2.scan(line);
+            match scan.value() {
+                Some(value) => println!("{}: {} -> {}", line, scan.describe(), value),
+                None => println!("{}: no digits", line),
+            }
+        }
+    }
+
+    let mut parts = vec![("Part 1", part1), ("Part 2", part2)];
+    parts.extend(custom.map(|custom| ("Custom", custom)));
+    for (name, calibrator) in parts {
+        let total = calibrator
+            .total(input.lines(), policy)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", name, e)))?;
+        match (total.without_digits, policy) {
+            (0, _) => println!("{}: {}", name, total.sum),
+            (n, Policy::Skip) => println!("{}: {} ({} lines without digits skipped)", name, total.sum, n),
+            (n, _) => println!("{}: {} ({} lines without digits counted as 0)", name, total.sum, n),
+        }
+    }
 
     Ok(())
 }
+
+fn main() -> io::Result<()> {
+    sum_calibration_values()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part1_tests() {
+        let calibrator = Calibrator::new(Vocabulary::numerals());
+        let lines = ["1abc2", "pqr3stu8vwx", "a1b2c3d4e5f", "treb7uchet"];
+        assert_eq!(lines.iter().map(|l| calibrator.value(l).unwrap()).collect::<Vec<_>>(), [12, 38, 15, 77]);
+        assert_eq!(calibrator.value("two1nine"), Some(11));
+        assert_eq!(calibrator.value("abcdef"), None);
+    }
+
+    #[test]
+    fn part2_tests() {
+        let calibrator = Calibrator::new(Vocabulary::english());
+        let lines = ["two1nine", "eightwothree", "abcone2threexyz", "xtwone3four", "4nineeightseven2", "zoneight234", "7pqrstsixteen"];
+        let values: Vec<u32> = lines.iter().map(|l| calibrator.value(l).unwrap()).collect();
+        assert_eq!(values, [29, 83, 13, 24, 42, 14, 76]);
+        assert_eq!(values.iter().sum::<u32>(), 281);
+        assert_eq!(calibrator.value("eightwo"), Some(82));
+    }
+
+    #[test]
+    fn no_digit_lines() {
+        let example = ["two1nine", "eightwothree", "abcone2threexyz", "xtwone3four", "4nineeightseven2", "zoneight234", "7pqrstsixteen"];
+        let part1 = Calibrator::new(Vocabulary::numerals()).with_reference(&Vocabulary::english());
+        assert_eq!(part1.calibrate("eightwothree"), Calibration::OnlyWords);
+        assert_eq!(part1.calibrate("abc"), Calibration::NoDigits);
+        assert_eq!(part1.calibrate("a1"), Calibration::Value(11));
+
+        assert_eq!(part1.total(example, Policy::Skip), Ok(Total { sum: 209, counted: 6, without_digits: 1 }));
+        assert_eq!(part1.total(example, Policy::Zero), Ok(Total { sum: 209, counted: 7, without_digits: 1 }));
+        let error = part1.total(example, Policy::Error).unwrap_err();
+        assert_eq!(error, Error { line: 2, calibration: Calibration::OnlyWords });
+        assert_eq!(error.to_string(), "line 2 only has digits this vocabulary doesn't read");
+        assert_eq!(part1.total(["1", "", "2"], Policy::Error).unwrap_err().to_string(), "line 2 has no digits");
+
+        let part2 = Calibrator::new(Vocabulary::english()).with_reference(&Vocabulary::english());
+        assert_eq!(part2.total(example, Policy::Error), Ok(Total { sum: 281, counted: 7, without_digits: 0 }));
+        assert_eq!(part2.calibrate("abc"), Calibration::NoDigits);
+
+        // Without a reference, nothing is left out.
+        assert_eq!(Calibrator::new(Vocabulary::numerals()).calibrate("eightwothree"), Calibration::NoDigits);
+
+        // The reference decides what counts, whatever the language.
+        let german = Vocabulary::from_table("eins 1\nzwei 2\ndrei 3").unwrap();
+        let mut reference = Vocabulary::english();
+        reference.add(german.words.clone());
+        let part1 = Calibrator::new(Vocabulary::numerals()).with_reference(&reference);
+        assert_eq!(part1.calibrate("xzweix"), Calibration::OnlyWords);
+        let german = Calibrator::new(german).with_reference(&reference);
+        assert_eq!(german.calibrate("x2x"), Calibration::OnlyWords);
+        assert_eq!(german.calibrate("xonex"), Calibration::OnlyWords);
+        assert_eq!(german.calibrate("xeinsx"), Calibration::Value(11));
+        assert_eq!(german.calibrate("xyz"), Calibration::NoDigits);
+    }
+
+    #[test]
+    fn scan_tests() {
+        let calibrator = Calibrator::new(Vocabulary::english());
+        let scan = calibrator.scan("xtwone3four");
+        let spans: Vec<(usize, usize, u32)> = scan.tokens.iter().map(|t| (t.start, t.end, t.value)).collect();
+        assert_eq!(spans, [(1, 4, 2), (3, 6, 1), (6, 7, 3), (7, 11, 4)]);
+        assert_eq!((scan.first, scan.last), (Some(0), Some(3)));
+
+        assert_eq!(scan.describe(), "[1..4=2] 3..6=1 6..7=3 [7..11=4]");
+
+        let scan = calibrator.scan("eightwo");
+        assert_eq!((scan.tokens[scan.first.unwrap()].start, scan.tokens[scan.last.unwrap()].start), (0, 4));
+        assert_eq!(scan.value(), Some(82));
+
+        let scan = calibrator.scan("abc");
+        assert_eq!(scan, LineScan { tokens: vec![], first: None, last: None });
+        assert_eq!(scan.value(), None);
+    }
+
+    // The two-regex approach the scanner replaced: the last digit is the first match of the
+    // reversed words in the reversed line.
+    fn regex_calibrator(words: &[(String, u32)]) -> impl Fn(&str) -> Option<u32> + '_ {
+        let reversed = |s: &str| s.chars().rev().collect::<String>();
+        let mut sorted: Vec<&str> = words.iter().map(|(w, _)| w.as_str()).collect();
+        sorted.sort_by_key(|w| std::cmp::Reverse(w.len()));
+        let alternation = |words: Vec<String>| regex::Regex::new(&words.join("|")).unwrap();
+        let forward = alternation(sorted.iter().map(|w| regex::escape(w)).collect());
+        let backward = alternation(sorted.iter().map(|w| regex::escape(&reversed(w))).collect());
+        let value = move |w: &str| words.iter().find(|(word, _)| word == w).unwrap().1;
+
+        move |line| {
+            let first = forward.find(line)?.as_str();
+            let last = reversed(backward.find(&reversed(line))?.as_str());
+            Some(join_digits(value(first), value(&last)))
+        }
+    }
+
+    #[test]
+    fn scan_matches_regex() {
+        let input = fs::read_to_string("input.txt").unwrap();
+        for vocabulary in [Vocabulary::numerals(), Vocabulary::english()] {
+            let calibrator = Calibrator::new(vocabulary.clone());
+            let regex_value = regex_calibrator(&vocabulary.words);
+            for line in input.lines().chain(["twone", "oneight", "sevenine", "x"]) {
+                assert_eq!(calibrator.value(line), regex_value(line), "{}", line);
+                assert_eq!(calibrator.scan(line).value(), regex_value(line), "{}", line);
+            }
+        }
+    }
+
+    // cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_scan() {
+        let input = fs::read_to_string("input.txt").unwrap().repeat(100);
+        let vocabulary = Vocabulary::english();
+
+        let timer = std::time::Instant::now();
+        let calibrator = Calibrator::new(vocabulary.clone());
+        let scanned: u32 = input.lines().filter_map(|l| calibrator.value(l)).sum();
+        let scan_time = timer.elapsed();
+
+        let timer = std::time::Instant::now();
+        let regex_value = regex_calibrator(&vocabulary.words);
+        let matched: u32 = input.lines().filter_map(regex_value).sum();
+        let regex_time = timer.elapsed();
+
+        assert_eq!(scanned, matched);
+        println!("Scanner {:?}, regexes {:?}", scan_time, regex_time);
+    }
+
+    #[test]
+    fn custom_vocabulary() {
+        let german = Vocabulary::from_table("eins 1\nzwei 2\ndrei 3\nvier 4\nfünf 5\nsechs 6\nsieben 7\nacht 8\nneun 9").unwrap();
+        let calibrator = Calibrator::new(german);
+        assert_eq!(calibrator.value("xachtzweifünfy"), Some(85));
+        assert_eq!(calibrator.value("one2three"), None);
+
+        // Longer words win where they overlap shorter ones, and values past 9 are written out in
+        // full.
+        let mut extended = Vocabulary::english();
+        extended.add(Vocabulary::from_table("ten 10\neleven 11\nseventeen 17").unwrap().words);
+        let calibrator = Calibrator::new(extended);
+        assert_eq!(calibrator.value("seventeenx3"), Some(173));
+        assert_eq!(calibrator.value("1xeleven"), Some(111));
+        assert_eq!(calibrator.value("tenzero"), Some(100));
+        assert_eq!(calibrator.scan("elevenxten").value(), Some(1110));
+        assert_eq!(join_digits(9999, 9999), 99999999);
+
+        assert!(Vocabulary::from_table("eins").is_none());
+        assert!(Vocabulary::from_table("eins x").is_none());
+        assert!(Vocabulary::from_table("lots 10000").is_none());
+    }
+}
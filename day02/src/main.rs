@@ -1,104 +1,352 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::str::FromStr;
 use std::fs::File;
 use std::io::{self, prelude::*, BufReader};
+use std::env;
 
-#[derive(Debug, Clone)]
-pub struct Error;
+// What's wrong with a game line, or a bag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // No `Game N:` at the start.
+    MissingId,
+    BadCount(String),
+    UnknownColour(String),
+    DuplicateColour(String),
+    // Anything else that doesn't fit `Game N: a colour, b colour; c colour, ...`.
+    StrayText(String),
+}
 
-#[derive(Debug, Default)]
-pub struct Round {
-    red: u32,
-    green: u32,
-    blue: u32
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingId => write!(f, "missing `Game N:`"),
+            Error::BadCount(count) => write!(f, "bad count {:?}", count),
+            Error::UnknownColour(colour) => write!(f, "unknown colour {:?}", colour),
+            Error::DuplicateColour(colour) => write!(f, "{} appears twice in one round", colour),
+            Error::StrayText(text) => write!(f, "unexpected {:?}", text),
+        }
+    }
+}
+
+// How many cubes there are of each colour.  Colours that aren't mentioned have none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CubeSet {
+    counts: BTreeMap<String, u32>,
 }
 
-impl FromStr for Round {
+impl CubeSet {
+    fn get(&self, colour: &str) -> u32 {
+        self.counts.get(colour).copied().unwrap_or(0)
+    }
+
+    // Whether every colour in other fits in this set.
+    fn contains(&self, other: &CubeSet) -> bool {
+        other.counts.iter().all(|(colour, count)| *count <= self.get(colour))
+    }
+
+    // The most of each colour in either set.
+    fn max(&self, other: &CubeSet) -> CubeSet {
+        let mut counts = self.counts.clone();
+        for (colour, count) in &other.counts {
+            let mine = counts.entry(colour.clone()).or_insert(0);
+            *mine = (*mine).max(*count);
+        }
+        CubeSet { counts }
+    }
+
+    // The counts of the given colours multiplied together.
+    fn power(&self, colours: &[&str]) -> u32 {
+        colours.iter().map(|colour| self.get(colour)).product()
+    }
+}
+
+impl FromStr for CubeSet {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (mut red, mut green, mut blue) = (0, 0, 0);
-        for color_count_str in s.split(",") {
-            let Some((count_str, color_name)) = color_count_str.trim().split_once(" ") else { continue; };
-            let count: u32 = count_str.parse().unwrap();
-            match color_name {
-                "red" => red += count,
-                "green" => green += count,
-                "blue" => blue += count,
-                _ => unreachable!("Only red / green/ blue should exist.")
-            }
-        }
-        Ok(Self { red, green, blue })
+        Parser::any_colour().cube_set(s)
     }
 }
 
+impl fmt::Display for CubeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts: Vec<String> = self.counts.iter().map(|(colour, count)| format!("{} {}", count, colour)).collect();
+        write!(f, "{}", counts.join(", "))
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct Game {
     id: u32,
-    rounds: Vec<Round>
+    rounds: Vec<CubeSet>
 }
 
 impl Game {
-    fn possible(&self, max_red: u32, max_green: u32, max_blue: u32) -> bool {
-        for round in &self.rounds {
-            if round.red > max_red || round.blue > max_blue || round.green > max_green { return false; }
-        }
-        true
+    // The fewest cubes of each colour the bag could have held.
+    fn min_bag(&self) -> CubeSet {
+        self.rounds.iter().fold(CubeSet::default(), |bag, round| bag.max(round))
     }
 
-    fn power_min_cubes(&self) -> u32 {
-        let (mut min_red, mut min_green, mut min_blue) = (0, 0, 0);
-        for round in &self.rounds {
-            if round.red > min_red { min_red = round.red; }
-            if round.green > min_green { min_green = round.green; }
-            if round.blue > min_blue { min_blue = round.blue; }
-        }
+    fn possible(&self, bag: &CubeSet) -> bool {
+        self.rounds.iter().all(|round| bag.contains(round))
+    }
 
-        min_red * min_green * min_blue
+    fn power_min_cubes(&self) -> u32 {
+        self.min_bag().power(&["red", "green", "blue"])
     }
 }
 
 impl FromStr for Game {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut id = 0;
-        let mut rounds = Vec::new();
+        Parser::standard().game(s)
+    }
+}
+
+// Reads games and bags.  A strict parser wants exactly `Game N: a colour, b colour; ...`, with
+// only the colours it knows (any, if it wasn't given a list) and none of them twice in a round.
+// A lenient one takes any colour, adds up repeats, and skips draws it can't make sense of.
+pub struct Parser {
+    colours: Option<Vec<String>>,
+    strict: bool,
+}
+
+impl Parser {
+    fn strict(colours: &[&str]) -> Self {
+        Self { colours: Some(colours.iter().map(|c| c.to_string()).collect()), strict: true }
+    }
+
+    fn standard() -> Self {
+        Self::strict(&["red", "green", "blue"])
+    }
+
+    // Strict about the format, but any word is a colour.
+    fn any_colour() -> Self {
+        Self { colours: None, strict: true }
+    }
+
+    fn lenient() -> Self {
+        Self { colours: None, strict: false }
+    }
+
+    fn game(&self, line: &str) -> Result<Game, Error> {
+        let (head, rounds_str) = line.split_once(':').ok_or(Error::MissingId)?;
+        let id_str = if self.strict {
+            head.strip_prefix("Game ").ok_or(Error::MissingId)?
+        } else {
+            head.trim().strip_prefix("Game").ok_or(Error::MissingId)?.trim()
+        };
+        if !id_str.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::MissingId);
+        }
+        let id = id_str.parse().map_err(|_| Error::MissingId)?;
+
+        let rounds_str = match (self.strict, rounds_str.strip_prefix(' ')) {
+            (true, Some(rest)) => rest,
+            (true, None) => return Err(Error::StrayText(rounds_str.to_string())),
+            (false, _) => rounds_str,
+        };
+        let separator = if self.strict { "; " } else { ";" };
+        let rounds = rounds_str.split(separator).map(|round| self.cube_set(round)).collect::<Result<_, _>>()?;
+
+        Ok(Game { id, rounds })
+    }
 
-        for line in s.lines() {
-            let Some((game_id_str, rounds_str)) = line.split_once(":") else { continue; };
-            let Some((_, id_str)) = game_id_str.split_once(" ") else { continue; };
-            id = id_str.parse().unwrap();
+    // A single round (or bag): `a colour, b colour, ...`.
+    fn cube_set(&self, s: &str) -> Result<CubeSet, Error> {
+        let mut counts = BTreeMap::new();
+        if !self.strict {
+            for draw in s.split(',') {
+                let Some((count_str, colour)) = draw.trim().split_once(' ') else { continue; };
+                let Ok(count) = count_str.parse::<u32>() else { continue; };
+                *counts.entry(colour.trim().to_string()).or_insert(0) += count;
+            }
+            return Ok(CubeSet { counts });
+        }
 
-            for round_str in rounds_str.split(";") {
-                rounds.push(Round::from_str(round_str).unwrap());
+        for draw in s.split(", ") {
+            let (count_str, colour) = draw.split_once(' ').ok_or_else(|| Error::StrayText(draw.to_string()))?;
+            if colour.is_empty() || !colour.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(Error::StrayText(draw.to_string()));
+            }
+            if count_str.is_empty() || !count_str.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(Error::BadCount(count_str.to_string()));
+            }
+            let count = count_str.parse().map_err(|_| Error::BadCount(count_str.to_string()))?;
+            if self.colours.as_ref().is_some_and(|colours| !colours.iter().any(|c| c == colour)) {
+                return Err(Error::UnknownColour(colour.to_string()));
+            }
+            if counts.insert(colour.to_string(), count).is_some() {
+                return Err(Error::DuplicateColour(colour.to_string()));
             }
         }
+        Ok(CubeSet { counts })
+    }
+}
+
+// The ids of the games that could have been played with the given bag.
+fn possible_games(games: &[Game], bag: &CubeSet) -> Vec<u32> {
+    games.iter().filter(|game| game.possible(bag)).map(|game| game.id).collect()
+}
+
+// The smallest bag every game could have been played with.
+fn smallest_bag_for_all(games: &[Game]) -> CubeSet {
+    games.iter().fold(CubeSet::default(), |bag, game| bag.max(&game.min_bag()))
+}
 
-        Ok(Self { id, rounds })
+// For each colour, the most cubes of it a bag can have and still be ruled out by some game,
+// however many of the other colours it has.
+fn largest_ruled_out(games: &[Game]) -> CubeSet {
+    let mut bag = smallest_bag_for_all(games);
+    bag.counts.retain(|_, count| *count > 0);
+    bag.counts.values_mut().for_each(|count| *count -= 1);
+    bag
+}
+
+// For each colour, how many games needed each number of cubes of it.
+fn colour_distributions(games: &[Game]) -> BTreeMap<String, BTreeMap<u32, usize>> {
+    let mut distributions: BTreeMap<String, BTreeMap<u32, usize>> = BTreeMap::new();
+    for game in games {
+        for (colour, count) in game.min_bag().counts {
+            *distributions.entry(colour).or_default().entry(count).or_insert(0) += 1;
+        }
     }
+    distributions
 }
 
-fn part1() {
+fn part1() -> io::Result<()> {
     let file = File::open("input.txt")
         .unwrap_or_else(|_| panic!("File 'input.txt' not readable.") );
 
-    let mut part1_total = 0;
-    let mut part2_total = 0;
+    // `cargo run -- --lenient` reads games that don't quite follow the format, and
+    // `cargo run -- "1 red, 2 green, 3 blue"` asks about a different bag.
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let parser = if args.first().is_some_and(|a| a == "--lenient") {
+        args.remove(0);
+        Parser::lenient()
+    } else {
+        Parser::standard()
+    };
+    let invalid = |what: String| io::Error::new(io::ErrorKind::InvalidData, what);
+
     let mut games = Vec::new();
-    
-    for line in BufReader::new(file).lines().filter_map(|line| line.ok()) {
-        let game = Game::from_str(&line).unwrap();
-        if game.possible(12, 13, 14) { part1_total += game.id; }
-        part2_total += game.power_min_cubes();
-        games.push(game);
+    for (i, line) in BufReader::new(file).lines().map_while(Result::ok).enumerate() {
+        games.push(parser.game(&line).map_err(|e| invalid(format!("line {}: {}", i + 1, e)))?);
     }
 
+    let bag_str = args.first().map(|a| a.as_str()).unwrap_or("12 red, 13 green, 14 blue");
+    let bag = CubeSet::from_str(bag_str).map_err(|e| invalid(format!("bag: {}", e)))?;
+
+    let part1_total: u32 = possible_games(&games, &bag).iter().sum();
+    let part2_total: u32 = games.iter().map(|game| game.power_min_cubes()).sum();
+
     println!("Part 1: {:?}", part1_total);
-    println!("Part 1: {:?}", part2_total);
+    println!("Part 2: {:?}", part2_total);
+
+    println!("Every game fits in: {}", smallest_bag_for_all(&games));
+    println!("Some game rules out: {}", largest_ruled_out(&games));
+    for (colour, distribution) in colour_distributions(&games) {
+        let counts: Vec<String> = distribution.iter().map(|(count, n)| format!("{}x{}", n, count)).collect();
+        println!("{}: {}", colour, counts.join(" "));
+    }
+
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
-    part1();
+    part1()
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+
+    fn example() -> Vec<Game> {
+        EXAMPLE.lines().map(|line| Game::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn part_tests() {
+        let games = example();
+        let bag = CubeSet::from_str("12 red, 13 green, 14 blue").unwrap();
+        assert_eq!(possible_games(&games, &bag), [1, 2, 5]);
+        assert_eq!(games.iter().map(|g| g.power_min_cubes()).sum::<u32>(), 2286);
+    }
+
+    #[test]
+    fn cube_set_queries() {
+        let games = example();
+        assert_eq!(games[0].min_bag().to_string(), "6 blue, 2 green, 4 red");
+        assert_eq!(smallest_bag_for_all(&games).to_string(), "15 blue, 13 green, 20 red");
+        assert_eq!(largest_ruled_out(&games).to_string(), "14 blue, 12 green, 19 red");
+
+        // Every game fits in the smallest bag for all; one fewer of any colour rules one out.
+        let all = smallest_bag_for_all(&games);
+        assert_eq!(possible_games(&games, &all).len(), games.len());
+        for (colour, count) in largest_ruled_out(&games).counts {
+            let mut bag = all.clone();
+            bag.counts.insert(colour, count);
+            assert!(possible_games(&games, &bag).len() < games.len());
+        }
+
+        // Colours other than red, green and blue are just more colours.
+        let game = Parser::any_colour().game("Game 7: 2 mauve, 1 red; 3 mauve").unwrap();
+        assert_eq!(game.min_bag().to_string(), "3 mauve, 1 red");
+        assert!(!game.possible(&CubeSet::from_str("12 red, 13 green, 14 blue").unwrap()));
+        assert!(game.possible(&CubeSet::from_str("3 mauve, 1 red").unwrap()));
+
+        // Bags take any colour, but not just anything.
+        assert_eq!(CubeSet::from_str("12 red, 13 grene").unwrap().get("grene"), 13);
+        assert_eq!(CubeSet::from_str("12 red, 13").unwrap_err(), Error::StrayText("13".to_string()));
+        assert_eq!(CubeSet::from_str("12 red,13 green").unwrap_err(), Error::StrayText("12 red,13 green".to_string()));
+        assert_eq!(CubeSet::from_str("x red").unwrap_err(), Error::BadCount("x".to_string()));
+
+        let distributions = colour_distributions(&games);
+        assert_eq!(distributions["red"], BTreeMap::from([(1, 1), (4, 1), (6, 1), (14, 1), (20, 1)]));
+        assert_eq!(distributions["green"], BTreeMap::from([(2, 1), (3, 3), (13, 1)]));
+    }
+
+    #[test]
+    fn strict_parsing() {
+        let game = Game::from_str("Game 12: 3 blue, 4 red; 2 green").unwrap();
+        assert_eq!((game.id, game.rounds.len()), (12, 2));
+        assert_eq!(game.rounds[0].to_string(), "3 blue, 4 red");
+
+        let error = |line: &str| Game::from_str(line).unwrap_err();
+        assert_eq!(error("3 blue, 4 red"), Error::MissingId);
+        assert_eq!(error("Game: 3 blue"), Error::MissingId);
+        assert_eq!(error("Game x: 3 blue"), Error::MissingId);
+        assert_eq!(error("Round 1: 3 blue"), Error::MissingId);
+        assert_eq!(error("Game 1: 3 blue, 4 blue; 1 red"), Error::DuplicateColour("blue".to_string()));
+        assert_eq!(error("Game 1: 3 blue; 2 mauve"), Error::UnknownColour("mauve".to_string()));
+        assert_eq!(error("Game 1: three blue"), Error::BadCount("three".to_string()));
+        assert_eq!(error("Game 1: 3 blue, "), Error::StrayText("".to_string()));
+        assert_eq!(error("Game 1: 3 blue;4 red"), Error::StrayText("3 blue;4 red".to_string()));
+        assert_eq!(error("Game 1: 3 blue!"), Error::StrayText("3 blue!".to_string()));
+        assert_eq!(error("Game 1:3 blue"), Error::StrayText("3 blue".to_string()));
+        assert_eq!(error("Game 1: 2 mauve").to_string(), "unknown colour \"mauve\"");
+        assert_eq!(error("Game 1: 3 blue, 4 blue").to_string(), "blue appears twice in one round");
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let parser = Parser::lenient();
+        let game = parser.game("Game 3:3 blue,4 blue;  2 mauve, junk, x red").unwrap();
+        assert_eq!(game.id, 3);
+        assert_eq!(game.rounds.iter().map(|r| r.to_string()).collect::<Vec<_>>(), ["7 blue", "2 mauve"]);
+        assert_eq!(parser.game("3 blue").unwrap_err(), Error::MissingId);
+
+        // Lenient parsing reads well-formed games just as strict parsing does.
+        for line in EXAMPLE.lines() {
+            assert_eq!(parser.game(line).unwrap().rounds, Game::from_str(line).unwrap().rounds);
+        }
+    }
 }
@@ -1,7 +1,14 @@
-use std::cmp::{min,max};
+use std::cmp::min;
+use std::ops::Range;
 use std::fs::File;
 use std::io::{self, prelude::*, BufReader};
 use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::{env, fmt};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+pub struct Error;
 
 pub enum SchematicEntry {
     Digit(u8),
@@ -19,6 +26,16 @@ impl From<char> for SchematicEntry {
     }
 }
 
+impl fmt::Display for SchematicEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Digit(d) => write!(f, "{}", d),
+            Self::Symbol(c) => write!(f, "{}", c),
+            Self::Empty => write!(f, "."),
+        }
+    }
+}
+
 pub struct Schematic {
     data : Vec<Vec<SchematicEntry>>
 }
@@ -35,7 +52,7 @@ impl Schematic {
 
         let mut schematic = Schematic::new();
 
-        for line in BufReader::new(file).lines().filter_map(|line| line.ok()) {
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
             schematic.append_row(&line);
         }
         schematic
@@ -45,103 +62,244 @@ impl Schematic {
         self.data.push(s.chars().map(|c| c.into()).collect());
     }
 
-    fn adjacent_symbol(&self, r: i32, c: i32) -> Option<(char, i32, i32)> {
-        let last_row = (self.data.len() - 1) as i32;
-        let last_col = (self.data[0].len() - 1) as i32;
-    
-        for i in max(0, r-1) ..= min(last_row, r+1) {
-            for j in max(0, c-1) ..= min(last_col, c+1) {
-                if let SchematicEntry::Symbol(char) = self.data[i as usize][j as usize] {
-                    return Some((char, i, j));
+    // Every number in the schematic, once each, in reading order, with the symbols around it,
+    // or Error if one of them is too big for a u64.
+    fn part_numbers(&self) -> Result<Vec<PartNumber>, Error> {
+        let mut numbers = vec![];
+        for (r, row) in self.data.iter().enumerate() {
+            let mut c = 0;
+            while c < row.len() {
+                let start = c;
+                let mut value: u64 = 0;
+                while let Some(SchematicEntry::Digit(d)) = row.get(c) {
+                    value = value.checked_mul(10).and_then(|v| v.checked_add(*d as u64)).ok_or(Error)?;
+                    c += 1;
                 }
+                if c == start {
+                    c += 1;
+                    continue;
+                }
+                numbers.push(PartNumber { row: r, cols: start..c, value, symbols: self.symbols_around(r, start..c) });
             }
         }
-        None    
-    }
-}
-
-fn part1(schematic: &Schematic) -> u32 {
-    let mut sum: u32 = 0;
-    // Iterate through each cell.  If we finish a number, check whether it was 
-    // ever next to a symbol.  If so, add it to the sum.
-    for (r, row) in schematic.data.iter().enumerate() {
-        let mut current_part_id: u32 = 0;
-        let mut has_adjacent_symbol : bool = false;
-        for (c, entry) in row.iter().enumerate() {
-            match entry {
-                SchematicEntry::Digit(d) => {
-                    current_part_id *= 10;
-                    current_part_id += *d as u32;
-                    has_adjacent_symbol |= schematic.adjacent_symbol(r as i32, c as i32).is_some();
-                },
-                _ => {
-                    if current_part_id != 0 {
-                        if has_adjacent_symbol { sum += current_part_id; }
-                        current_part_id = 0;
-                    }
-                    has_adjacent_symbol = false;
+        Ok(numbers)
+    }
+
+    // Every symbol touching columns cols of row r, diagonals included, in reading order.
+    fn symbols_around(&self, r: usize, cols: Range<usize>) -> Vec<(char, usize, usize)> {
+        let mut symbols = vec![];
+        for i in r.saturating_sub(1) ..= min(self.data.len() - 1, r + 1) {
+            let row = &self.data[i];
+            for (j, entry) in row.iter().enumerate().take(cols.end + 1).skip(cols.start.saturating_sub(1)) {
+                if let SchematicEntry::Symbol(char) = entry {
+                    symbols.push((*char, i, j));
                 }
             }
         }
+        symbols
+    }
 
-        if current_part_id != 0 {
-            if has_adjacent_symbol { sum += current_part_id; }
+    // The schematic with notes after each row: the numbers on it that aren't part numbers, and
+    // the ratio of each gear on it under the rule.  With colour, part numbers are green, the
+    // others dimmed, and gears yellow.  Error if a number or a gear's ratio is too big, as for
+    // GearRule::ratios.
+    fn render(&self, rule: &GearRule, colour: bool) -> Result<String, Error> {
+        let mut styles: HashMap<(usize, usize), &str> = HashMap::new();
+        let mut notes: Vec<Vec<(usize, String)>> = vec![vec![]; self.data.len()];
+        for number in self.part_numbers()? {
+            let style = if number.is_part() { "1;32" } else { "2" };
+            for c in number.cols.clone() {
+                styles.insert((number.row, c), style);
+            }
+            if !number.is_part() {
+                notes[number.row].push((number.cols.start, format!("{} has no symbol", number.value)));
+            }
+        }
+        for ((r, c), ratio) in rule.ratios(self)? {
+            styles.insert((r, c), "1;33");
+            let note = format!("gear at {}: {}", c, ratio);
+            notes[r].push((c, if colour { format!("\x1b[33m{}\x1b[0m", note) } else { note }));
         }
+
+        let lines: Vec<String> = self.data.iter().zip(notes).enumerate().map(|(r, (row, mut notes))| {
+            let mut line: String = row.iter().enumerate().map(|(c, entry)| match styles.get(&(r, c)) {
+                Some(style) if colour => format!("\x1b[{}m{}\x1b[0m", style, entry),
+                _ => entry.to_string(),
+            }).collect();
+            if !notes.is_empty() {
+                notes.sort();
+                let notes: Vec<String> = notes.into_iter().map(|(_, note)| note).collect();
+                line += &format!("   {}", notes.join(", "));
+            }
+            line
+        }).collect();
+
+        Ok(lines.join("\n"))
     }
-    sum
-}
-
-fn part2(schematic: &Schematic) -> u32 {
-    let mut gears = HashMap::new();
-
-    // In this case, we keep a list of which numbers were next to each gear.  When we're done scanning
-    // the schematic, we then sum up any "gear ratios" that were next to exactly two gears.
-    for (r, row) in schematic.data.iter().enumerate() {
-        let mut current_part_id: u32 = 0;
-        let mut adjacent_gear : Option<(i32, i32)> = None;
-        for (c, entry) in row.iter().enumerate() {
-            match entry {
-                SchematicEntry::Digit(d) => {
-                    current_part_id *= 10;
-                    current_part_id += *d as u32;
-                    if let Some(('*', r, c)) = schematic.adjacent_symbol(r as i32, c as i32) {
-                        adjacent_gear = Some((r, c));
-
-                    }
-                },
-                _ => {
-                    if current_part_id != 0 {
-                        if let Some(rc) = adjacent_gear {
-                            gears.entry(rc).or_insert(vec![]).push(current_part_id);
-                        }
-                        current_part_id = 0;
-                    }
-                    adjacent_gear = None;
-                }
+}
+
+impl fmt::Display for Schematic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (r, row) in self.data.iter().enumerate() {
+            if r > 0 {
+                writeln!(f)?;
+            }
+            for entry in row {
+                write!(f, "{}", entry)?;
             }
         }
+        Ok(())
+    }
+}
+
+// A number in the schematic: the row it's on, the columns it covers, its value, and every
+// symbol next to it (with its row and column).  It's a part number if there are any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartNumber {
+    row: usize,
+    cols: Range<usize>,
+    value: u64,
+    symbols: Vec<(char, usize, usize)>,
+}
+
+impl PartNumber {
+    fn is_part(&self) -> bool {
+        !self.symbols.is_empty()
+    }
+}
+
+// Error if a number, or their sum, is too big for a u64.
+fn part1(schematic: &Schematic) -> Result<u64, Error> {
+    schematic.part_numbers()?.iter().filter(|n| n.is_part()).try_fold(0u64, |sum, n| sum.checked_add(n.value)).ok_or(Error)
+}
+
+// Which symbols are gears, how many numbers one needs next to it, and how they make its ratio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GearRule {
+    symbols: Vec<char>,
+    neighbours: Neighbours,
+    combine: Combine,
+}
 
-        if current_part_id != 0 {
-            if let Some(rc) = adjacent_gear {
-                gears.entry(rc).or_insert(vec![]).push(current_part_id);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbours {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+    Product,
+    Sum,
+    Max,
+}
+
+impl GearRule {
+    // `*` next to exactly two numbers, multiplied together.
+    fn standard() -> Self {
+        GearRule { symbols: vec!['*'], neighbours: Neighbours::Exactly(2), combine: Combine::Product }
+    }
+
+    // The numbers next to each gear symbol, by its position.  A number next to two of them
+    // counts for both.  Error if a number is too big, as for Schematic::part_numbers.
+    fn gears(&self, schematic: &Schematic) -> Result<HashMap<(usize, usize), Vec<u64>>, Error> {
+        let mut gears: HashMap<(usize, usize), Vec<u64>> = HashMap::new();
+        for number in schematic.part_numbers()? {
+            for (_, r, c) in number.symbols.iter().filter(|(char, _, _)| self.symbols.contains(char)) {
+                gears.entry((*r, *c)).or_default().push(number.value);
             }
         }
+        Ok(gears)
+    }
+
+    // The ratio of every gear with the right number of numbers next to it, or Error if a number
+    // or one of the ratios is too big for a u64.
+    fn ratios(&self, schematic: &Schematic) -> Result<HashMap<(usize, usize), u64>, Error> {
+        self.gears(schematic)?
+            .into_iter()
+            .filter(|(_, values)| match self.neighbours {
+                Neighbours::Exactly(n) => values.len() == n,
+                Neighbours::AtLeast(n) => values.len() >= n,
+            })
+            .map(|(rc, values)| {
+                let mut values = values.into_iter();
+                let ratio = match self.combine {
+                    Combine::Product => values.try_fold(1u64, |ratio, v| ratio.checked_mul(v)),
+                    Combine::Sum => values.try_fold(0u64, |ratio, v| ratio.checked_add(v)),
+                    Combine::Max => values.max(),
+                };
+                Ok((rc, ratio.ok_or(Error)?))
+            })
+            .collect()
+    }
+
+    fn total(&self, schematic: &Schematic) -> Result<u64, Error> {
+        self.ratios(schematic)?.values().try_fold(0u64, |total, ratio| total.checked_add(*ratio)).ok_or(Error)
     }
+}
+
+// Symbols, count and combination separated by spaces, e.g. `*# 2+ sum`: gears are `*` or `#`
+// with at least two numbers next to them, and their ratio is the sum.  `2` is exactly two.
+impl FromStr for GearRule {
+    type Err = Error;
 
-    let mut sum = 0;
-    for (_, values) in &gears {
-        if values.len() == 2 {
-            sum += values[0] * values[1];
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [symbols, count, combine] = s.split_whitespace().collect::<Vec<_>>()[..] else { return Err(Error); };
+        let neighbours = match count.strip_suffix('+') {
+            Some(n) => Neighbours::AtLeast(n.parse().map_err(|_| Error)?),
+            None => Neighbours::Exactly(count.parse().map_err(|_| Error)?),
+        };
+        // Only symbols with a number next to them are ever looked at, so none would match 0.
+        if let Neighbours::Exactly(0) | Neighbours::AtLeast(0) = neighbours {
+            return Err(Error);
+        }
+        let combine = match combine {
+            "product" => Combine::Product,
+            "sum" => Combine::Sum,
+            "max" => Combine::Max,
+            _ => return Err(Error),
+        };
+        if symbols.chars().any(|c| c == '.' || c.is_ascii_digit()) {
+            return Err(Error);
         }
+        Ok(GearRule { symbols: symbols.chars().collect(), neighbours, combine })
     }
+}
 
-    sum
+fn part2(schematic: &Schematic) -> Result<u64, Error> {
+    GearRule::standard().total(schematic)
 }
 
 fn main() -> io::Result<()> {
-    let schematic = Schematic::from_file("input.txt");
-    println!("Part 1 sum: {}", part1(&schematic));
-    println!("Part 2 sum: {}", part2(&schematic));
+    // `cargo run -- --input FILE` reads a different schematic, and `-- --gears RULE` also
+    // totals the gear ratios under a different rule (see GearRule::from_str).  `-- --annotate`
+    // shows which numbers counted, under that rule or the standard one.
+    let usage = || io::Error::new(io::ErrorKind::InvalidInput, "usage: day03 [--input FILE] [--gears RULE] [--annotate]");
+    let mut args = env::args().skip(1);
+    let mut input_path = "input.txt".to_string();
+    let mut rule = None;
+    let mut annotate = false;
+    while let Some(option) = args.next() {
+        match option.as_str() {
+            "--annotate" => annotate = true,
+            "--input" => input_path = args.next().ok_or_else(usage)?,
+            "--gears" => rule = Some(GearRule::from_str(&args.next().ok_or_else(usage)?)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bad gear rule"))?),
+            _ => return Err(usage()),
+        }
+    }
+
+    let too_big = |_| io::Error::new(io::ErrorKind::InvalidData, "numbers or gear ratios too big");
+    let schematic = Schematic::from_file(&input_path);
+    println!("Part 1 sum: {}", part1(&schematic).map_err(too_big)?);
+    println!("Part 2 sum: {}", part2(&schematic).map_err(too_big)?);
+    if let Some(rule) = &rule {
+        println!("Gear ratio sum: {}", rule.total(&schematic).map_err(too_big)?);
+    }
+    if annotate {
+        let rule = rule.unwrap_or_else(GearRule::standard);
+        println!("{}", schematic.render(&rule, io::stdout().is_terminal()).map_err(too_big)?);
+    }
 
     Ok(())
 }
@@ -149,9 +307,9 @@ fn main() -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
-    #[test]
-    fn test_sum() {
+    fn example() -> Schematic {
         let mut example = Schematic::new();
         example.append_row("467..114.");
         example.append_row("...*.....");
@@ -163,8 +321,106 @@ mod tests {
         example.append_row("......755");
         example.append_row("...$.*...");
         example.append_row(".664.598.");
+        example
+    }
+
+    #[test]
+    fn test_sum() {
+        let example = example();
+        assert_eq!(part1(&example).unwrap(), 4361);
+        assert_eq!(part2(&example).unwrap(), 467835);
+
+        let numbers = example.part_numbers().unwrap();
+        assert_eq!(numbers.len(), 10);
+        assert_eq!(numbers[0], PartNumber { row: 0, cols: 0..3, value: 467, symbols: vec![('*', 1, 3)] });
+        assert_eq!(numbers[1], PartNumber { row: 0, cols: 5..8, value: 114, symbols: vec![] });
+        let hashes: Vec<u64> = numbers.iter().filter(|n| n.symbols.iter().any(|s| s.0 == '#')).map(|n| n.value).collect();
+        assert_eq!(hashes, [633]);
+    }
+
+    #[test]
+    fn shared_symbols() {
+        // 12 touches both gears, and 7 is at the right edge.
+        let mut example = Schematic::new();
+        example.append_row("3*12*4");
+        example.append_row("#....7");
+        let numbers = example.part_numbers().unwrap();
+        assert_eq!(numbers.iter().map(|n| (n.value, n.cols.clone())).collect::<Vec<_>>(), [(3, 0..1), (12, 2..4), (4, 5..6), (7, 5..6)]);
+        assert_eq!(numbers[0].symbols, [('*', 0, 1), ('#', 1, 0)]);
+        assert_eq!(numbers[1].symbols, [('*', 0, 1), ('*', 0, 4)]);
+        assert_eq!(numbers[3].symbols, [('*', 0, 4)]);
+        // The second gear has three numbers next to it, so only the first counts.
+        assert_eq!(part2(&example).unwrap(), 3 * 12);
+    }
+
+    #[test]
+    fn long_numbers() {
+        // Eleven digits is past a u32, but the largest u64 is still fine and one more isn't.
+        let schematic = |row: &str| {
+            let mut example = Schematic::new();
+            example.append_row(row);
+            example
+        };
+        assert_eq!(part1(&schematic("12345678901*4294967296")).unwrap(), 12345678901 + 4294967296);
+        assert_eq!(schematic("1*18446744073709551615").part_numbers().unwrap()[1].value, u64::MAX);
+        assert!(part1(&schematic("1*18446744073709551615")).is_err());
+
+        let example = schematic("18446744073709551616*1");
+        assert!(example.part_numbers().is_err());
+        assert!(part2(&example).is_err());
+        assert!(example.render(&GearRule::standard(), false).is_err());
+    }
+
+    #[test]
+    fn gear_rules() {
+        let mut example = Schematic::new();
+        example.append_row("3*12*4");
+        example.append_row("#....7");
+        example.append_row("..5#..");
+
+        assert_eq!(GearRule::from_str("* 2 product").unwrap(), GearRule::standard());
+        let total = |rule: &str| GearRule::from_str(rule).unwrap().total(&example).unwrap();
+        assert_eq!(total("* 2 product"), 36);
+        assert_eq!(total("* 3 product"), 12 * 4 * 7);
+        assert_eq!(total("* 2+ sum"), (3 + 12) + (12 + 4 + 7));
+        assert_eq!(total("* 2+ max"), 12 + 12);
+        assert_eq!(total("# 1 product"), 3 + 5);
+        assert_eq!(total("*# 1+ max"), 12 + 12 + 3 + 5);
+
+        let ratios = GearRule::from_str("#* 3 sum").unwrap().ratios(&example).unwrap();
+        assert_eq!(ratios, HashMap::from([((0, 4), 23)]));
 
-        assert_eq!(part1(&example), 4361);
-        assert_eq!(part2(&example), 467835);
+        // 999^4 fits in a u64; a billion to the fourth doesn't.
+        let mut big = Schematic::new();
+        for row in ["999.999", "...*...", "999.999"] {
+            big.append_row(row);
+        }
+        let rule = GearRule::from_str("* 2+ product").unwrap();
+        assert_eq!(rule.total(&big).unwrap(), 999u64.pow(4));
+        let mut bigger = Schematic::new();
+        for row in ["1000000000.1000000000", "..........*..........", "1000000000.1000000000"] {
+            bigger.append_row(row);
+        }
+        assert!(rule.total(&bigger).is_err());
+        assert_eq!(GearRule::from_str("* 2+ sum").unwrap().total(&bigger).unwrap(), 4_000_000_000);
+
+        for bad in ["", "* 2", "* two product", "* 2 mean", "*. 2 sum", "* 2 sum extra", "* 0 sum", "* 0+ sum"] {
+            assert!(GearRule::from_str(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn render_tests() {
+        let example = example();
+        assert_eq!(example.to_string(), fs::read_to_string("test_input.txt").unwrap().trim_end());
+        assert_eq!(SchematicEntry::from('#').to_string(), "#");
+
+        let rendered = example.render(&GearRule::standard(), false).unwrap();
+        assert_eq!(rendered, fs::read_to_string("test_render.txt").unwrap().trim_end());
+
+        let coloured = example.render(&GearRule::standard(), true).unwrap();
+        assert!(coloured.starts_with("\x1b[1;32m4\x1b[0m\x1b[1;32m6\x1b[0m\x1b[1;32m7\x1b[0m..\x1b[2m1\x1b[0m"));
+        assert!(coloured.contains("\x1b[1;33m*\x1b[0m"));
+        assert!(coloured.contains("\x1b[33mgear at 3: 16345\x1b[0m"));
     }
-}
\ No newline at end of file
+}
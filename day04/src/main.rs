@@ -1,4 +1,4 @@
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeMap, HashSet, HashMap};
 use std::str::FromStr;
 use std::fs::File;
 use std::io::{self, prelude::*, BufReader};
@@ -16,12 +16,15 @@ pub struct GameCard {
 impl FromStr for GameCard {
     type Err = Error;
 
+    // `Card N: winning numbers | our numbers`, any number of each.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts : Vec<&str> = s.split_ascii_whitespace().collect();
-        let id : u32 = parts[1].strip_suffix(":").unwrap().parse().unwrap();
-        let winners: HashSet<u32> = parts[2..=11].iter().map(|x| x.parse().unwrap()).collect();
-        let mine: HashSet<u32> = parts[13..].iter().map(|x| x.parse().unwrap()).collect();
-        Ok(GameCard {id, winners, mine})
+        let (card_str, numbers_str) = s.split_once(':').ok_or(Error)?;
+        let (winners_str, mine_str) = numbers_str.split_once('|').ok_or(Error)?;
+        let id : u32 = card_str.trim().strip_prefix("Card").ok_or(Error)?.trim().parse().map_err(|_| Error)?;
+        let numbers = |s: &str| -> Result<HashSet<u32>, Error> {
+            s.split_ascii_whitespace().map(|x| x.parse().map_err(|_| Error)).collect()
+        };
+        Ok(GameCard {id, winners: numbers(winners_str)?, mine: numbers(mine_str)?})
     }
 }
 
@@ -30,52 +33,131 @@ impl GameCard {
         self.winners.intersection(&self.mine).count() as u32
     }
 
-    fn score(&self) -> u32 {
+    // None if the card matches more than 64 numbers, which scores more than a u64 can hold.
+    fn score(&self) -> Option<u64> {
         let count = self.matches();
-        if count > 0 { return 1 << (count - 1); } else { return 0; }
+        if count > 0 { 1u64.checked_shl(count - 1) } else { Some(0) }
     }
 
-    fn score_and_redeem(&self, gamecards: &HashMap<u32, GameCard>) -> Vec<GameCard> {
-        (self.id+1 ..= self.id + self.matches()).map(|id| gamecards[&id].clone()).collect()
+    // The ids of the cards this one wins copies of.
+    fn won_ids(&self) -> std::ops::RangeInclusive<u32> {
+        self.id + 1 ..= self.id + self.matches()
     }
 }
 
-fn day04() {
+// How many copies of each card we end up with, by id.  Cards only win copies of later cards, so
+// by the time we reach a card in id order we know how many copies of it there are, and each of
+// them wins one copy of every card it points at.
+fn card_copies(gamecards: &HashMap<u32, GameCard>) -> BTreeMap<u32, u64> {
+    let mut copies: BTreeMap<u32, u64> = gamecards.keys().map(|id| (*id, 1)).collect();
+    let ids: Vec<u32> = copies.keys().copied().collect();
+    for id in ids {
+        let count = copies[&id];
+        for won in gamecards[&id].won_ids() {
+            if let Some(won_copies) = copies.get_mut(&won) {
+                *won_copies += count;
+            }
+        }
+    }
+    copies
+}
+
+fn day04() -> io::Result<()> {
     let file = File::open("input.txt")
         .unwrap_or_else(|_| panic!("File 'input.txt' not readable.") );
 
     // Collect a HashMap of all the gamecards
-    let gamecards : HashMap<u32, GameCard> = BufReader::new(file).lines()
-        .filter_map(|line| (line.ok()))
-        .map(|line| GameCard::from_str(&line).unwrap())
-        .map(|card| (card.id, card))
-        .collect();
+    let mut gamecards : HashMap<u32, GameCard> = HashMap::new();
+    for (i, line) in BufReader::new(file).lines().map_while(Result::ok).enumerate() {
+        let card = GameCard::from_str(&line)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("bad card on line {}", i + 1)))?;
+        gamecards.insert(card.id, card);
+    }
     
     // Iterate through game cards, and sum up their scores.
-    let part1_total : u32 = gamecards.iter()
-        .map(|(_, card)| card.score())
-        .sum();
+    let part1_total : u64 = gamecards.values()
+        .try_fold(0u64, |total, card| total.checked_add(card.score()?))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "part 1 total is too big for a u64"))?;
 
     // Output part 1 answer
     println!("Part 1: {:?}", part1_total);
 
-    // Create a vector of the cards in order (since order matters for the first part)
-    let mut remaining_cards : Vec<GameCard> = (1..=gamecards.len() as u32).map(|id| gamecards[&id].clone()).collect();
-    let mut total_scratchcards = 0u32;
-
-    // As long as we have cards remaining, play them, keeping track of how many we create each round
-    while !remaining_cards.is_empty() {
-        total_scratchcards += remaining_cards.len() as u32;
-
-        remaining_cards = remaining_cards.iter()
-            .flat_map(|card| card.score_and_redeem(&gamecards))
-            .collect();
-    }
+    let total_scratchcards: u64 = card_copies(&gamecards).values().sum();
     println!("Part 2: {:?}", total_scratchcards);
+
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
-    day04();
+    day04()
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    fn example() -> HashMap<u32, GameCard> {
+        EXAMPLE.lines().map(|line| GameCard::from_str(line).unwrap()).map(|card| (card.id, card)).collect()
+    }
+
+    // Plays every copy of every card, as part 2 used to, counting how many of each we see.
+    fn simulate(gamecards: &HashMap<u32, GameCard>) -> BTreeMap<u32, u64> {
+        let mut seen = BTreeMap::new();
+        let mut remaining_cards: Vec<&GameCard> = (1..=gamecards.len() as u32).map(|id| &gamecards[&id]).collect();
+        while !remaining_cards.is_empty() {
+            for card in &remaining_cards {
+                *seen.entry(card.id).or_insert(0) += 1;
+            }
+            remaining_cards = remaining_cards.iter().flat_map(|card| card.won_ids().map(|id| &gamecards[&id])).collect();
+        }
+        seen
+    }
+
+    #[test]
+    fn card_copies_tests() {
+        let gamecards = example();
+        assert_eq!(gamecards.values().map(|card| card.score().unwrap()).sum::<u64>(), 13);
+
+        let copies = card_copies(&gamecards);
+        assert_eq!(copies.values().copied().collect::<Vec<_>>(), [1, 2, 4, 8, 14, 1]);
+        assert_eq!(copies.values().sum::<u64>(), 30);
+        assert_eq!(copies, simulate(&gamecards));
+    }
+
+    #[test]
+    fn parse_tests() {
+        let card = GameCard::from_str("Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1").unwrap();
+        assert_eq!(card.id, 3);
+        assert_eq!(card.winners, HashSet::from([1, 21, 53, 59, 44]));
+        assert_eq!(card.mine, HashSet::from([69, 82, 63, 72, 16, 21, 14, 1]));
+        assert_eq!(card.matches(), 2);
+
+        // The real input's layout: 10 winning numbers, 25 of ours, ids padded to three places.
+        let line = "Card   1: 57 76 72 11  8 28 15 38 54 46 | 77 87 71 98 40  7 84 43 61 64  5 50 19 83 79 99 36 47  4 95 30 44 37 55 26";
+        let card = GameCard::from_str(line).unwrap();
+        assert_eq!((card.id, card.winners.len(), card.mine.len()), (1, 10, 25));
+        assert_eq!(card.winners.intersection(&HashSet::from([57, 46, 8])).count(), 3);
+
+        // Cards can be any width, but past 64 matches the score doesn't fit in a u64.
+        let card = |n: u32| {
+            let numbers: Vec<String> = (1..=n).map(|n| n.to_string()).collect();
+            GameCard::from_str(&format!("Card 1: {0} | {0}", numbers.join(" "))).unwrap()
+        };
+        assert_eq!(card(40).score(), Some(1 << 39));
+        assert_eq!(card(64).score(), Some(1 << 63));
+        assert_eq!(card(65).score(), None);
+        assert_eq!(card(65).won_ids().count(), 65);
+
+        for bad in ["", "Card 1 41 48 | 83", "Card 1: 41 48 83", "Card x: 41 | 83", "Game 1: 41 | 83", "Card 1: 41 4x | 83", "Card 1: 41 | 83 | 86"] {
+            assert!(GameCard::from_str(bad).is_err(), "{:?}", bad);
+        }
+    }
 }
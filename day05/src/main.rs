@@ -5,7 +5,7 @@ use std::{io, fs, cmp};
 #[derive(Debug, Clone)]
 pub struct Error;
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Range {
     start: u64,
     length: u64,
@@ -62,7 +62,7 @@ impl RangedLookup {
             Range::new(overlap_end+1, range.end - overlap_end)
         );}
 
-        return (mutated, unprocessed)
+        (mutated, unprocessed)
     }
 }
 
@@ -89,27 +89,27 @@ impl FromStr for AlmanacSection {
 impl AlmanacSection {
     fn lookup(&self, value: u64) -> u64 {
         for lookup in &self.lookups {
-            let result = lookup.lookup(value);
-            if result.is_some() { return result.unwrap(); }
+            if let Some(result) = lookup.lookup(value) { return result; }
         }
         value // Unmapped values get passed straight through
     }
 
-    // This should really be a set, since ordering doesn't matter...  But this works.
-    fn range_lookup(&self, to_lookup : Range) -> HashSet<Range> {
-        let mut mutated_ranges: HashSet<Range> = HashSet::new();
+    // Ordering doesn't matter, but this can't be a set: pieces from different parts of the
+    // range can land on the same values, and a set would merge them.
+    fn range_lookup(&self, to_lookup : Range) -> Vec<Range> {
+        let mut mutated_ranges: Vec<Range> = Vec::new();
         let mut remainder = HashSet::from([to_lookup]);
         for lookup in &self.lookups {
             let mut new_remainders = HashSet::new();
             for range in remainder {
                 let (mutated_range, new_remainder) = lookup.range_lookup(range);
-                if let Some(r) = mutated_range { mutated_ranges.insert(r); }
+                if let Some(r) = mutated_range { mutated_ranges.push(r); }
                 new_remainders.extend(new_remainder);
             }
             remainder = new_remainders;
         }
         mutated_ranges.extend(remainder);// Unmapped values get passed straight through
-        return mutated_ranges;
+        mutated_ranges
     }
 }
 
@@ -144,10 +144,11 @@ impl Almanac {
         current_value
     }
 
-    fn locations_for_range(&self, range: Range) -> HashSet<Range> {
-        let mut ranges = HashSet::from([range]);
+    // A Vec for the same reason as AlmanacSection::range_lookup.
+    fn locations_for_range(&self, range: Range) -> Vec<Range> {
+        let mut ranges = vec![range];
         for section in &self.almanac_sections {
-            let mut next_ranges = HashSet::new();
+            let mut next_ranges = Vec::new();
             for range in ranges {
                 next_ranges.extend(section.range_lookup(range) );
             }
@@ -193,6 +194,7 @@ fn main() -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn part1_tests() {
@@ -228,7 +230,83 @@ mod tests {
             RangedLookup { destination: 50, source: Range::new(7, 3) }
         ] };
 
-        let mutated = section.range_lookup(Range::new(1, 12));
-        assert_eq!(mutated, HashSet::from([Range::new(1, 2), Range::new(5, 2), Range::new(10, 3), Range::new(10, 2), Range::new(50, 3)]));
+        let mut mutated = section.range_lookup(Range::new(1, 12));
+        mutated.sort();
+        assert_eq!(mutated, vec![Range::new(1, 2), Range::new(5, 2), Range::new(10, 2), Range::new(10, 3), Range::new(50, 3)]);
+
+        // 3 and 4 map onto 1 and 2, which pass through unchanged as well.
+        let section = AlmanacSection { lookups: vec![RangedLookup { destination: 1, source: Range::new(3, 2) }] };
+        let mut mutated = section.range_lookup(Range::new(1, 4));
+        mutated.sort();
+        assert_eq!(mutated, vec![Range::new(1, 2), Range::new(1, 2)]);
+    }
+
+    // Lookups with disjoint sources, as in the puzzle, from (gap before the source, source
+    // length, destination) triples.  Destinations are free to overlap anything.
+    fn section(triples: Vec<(u64, u64, u64)>) -> AlmanacSection {
+        let mut next_start = 0;
+        let mut lookups = vec![];
+        for (gap, length, destination) in triples {
+            let source = Range::new(next_start + gap, length);
+            next_start = source.end + 1;
+            lookups.push(RangedLookup { destination, source });
+        }
+        AlmanacSection { lookups }
+    }
+
+    fn points(ranges: impl IntoIterator<Item = Range>) -> Vec<u64> {
+        let mut points: Vec<u64> = ranges.into_iter().flat_map(|r| r.start..=r.end).collect();
+        points.sort();
+        points
+    }
+
+    proptest! {
+        #[test]
+        fn ranged_lookup_matches_points(
+            (destination, source_start, source_length) in (0..100u64, 0..100u64, 1..40u64),
+            (start, length) in (0..150u64, 1..60u64),
+        ) {
+            let lookup = RangedLookup { destination, source: Range::new(source_start, source_length) };
+            let range = Range::new(start, length);
+            let (mutated, unprocessed) = lookup.range_lookup(range.clone());
+
+            let (mapped, unmapped): (Vec<u64>, Vec<u64>) = (range.start..=range.end).partition(|p| lookup.lookup(*p).is_some());
+            prop_assert_eq!(points(mutated.clone()), points(mapped.iter().map(|p| Range::new(lookup.lookup(*p).unwrap(), 1))));
+            prop_assert_eq!(points(unprocessed.clone()), unmapped);
+
+            let total = mutated.map_or(0, |r| r.length) + unprocessed.iter().map(|r| r.length).sum::<u64>();
+            prop_assert_eq!(total, range.length);
+        }
+
+        #[test]
+        fn section_lookup_matches_points(
+            triples in prop::collection::vec((0..5u64, 1..10u64, 0..30u64), 0..5),
+            (start, length) in (0..40u64, 1..30u64),
+        ) {
+            let section = section(triples);
+            let range = Range::new(start, length);
+            let mapped = section.range_lookup(range.clone());
+
+            // Every point comes out exactly as often as it's reached one at a time.
+            let mut expected: Vec<u64> = (range.start..=range.end).map(|p| section.lookup(p)).collect();
+            expected.sort();
+            prop_assert_eq!(points(mapped.clone()), expected);
+            prop_assert_eq!(mapped.iter().map(|r| r.length).sum::<u64>(), range.length);
+        }
+
+        #[test]
+        fn almanac_lookup_matches_points(
+            sections in prop::collection::vec(prop::collection::vec((0..5u64, 1..10u64, 0..30u64), 0..5), 1..4),
+            (start, length) in (0..40u64, 1..30u64),
+        ) {
+            let almanac = Almanac { seeds: vec![], almanac_sections: sections.into_iter().map(section).collect() };
+            let range = Range::new(start, length);
+            let locations = almanac.locations_for_range(range.clone());
+
+            let mut expected: Vec<u64> = (range.start..=range.end).map(|p| almanac.location_for_seed(p)).collect();
+            expected.sort();
+            prop_assert_eq!(points(locations.clone()), expected);
+            prop_assert_eq!(locations.iter().map(|r| r.length).sum::<u64>(), range.length);
+        }
     }
 }
@@ -1,5 +1,13 @@
+#![allow(clippy::needless_return)]
+
+use picture::{heat_colour, Picture};
+use std::collections::HashSet;
+use std::io::IsTerminal;
 use std::str::FromStr;
-use std::{fs, io};
+use std::{env, fs, io};
+
+// Side length of a tile in exported images, in pixels.
+const TILE_PX: usize = 7;
 
 #[derive(Debug, Clone)]
 pub struct Error;
@@ -7,6 +15,9 @@ pub struct Error;
 #[derive(Debug, Clone)]
 enum Direction { North, East, South, West }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region { Loop, Inside, Outside }
+
 #[derive(Debug, Clone)]
 pub struct Map {
     start: (usize, usize), // row, col
@@ -17,7 +28,7 @@ impl FromStr for Map {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut start = (0usize, 0usize);
+        let mut start = None;
         let cells = s
             .lines()
             .enumerate()
@@ -25,18 +36,22 @@ impl FromStr for Map {
                 line.chars()
                     .enumerate()
                     .map(|(col, ch)| {
-                        let cell = Cell::from_char((row, col), ch);
+                        let cell = Cell::from_char((row, col), ch)?;
                         if cell.start_distance == Some(0) {
                             // Side effect in interation ... little shady.
-                            start = (row, col);
+                            start = Some((row, col));
                         }
-                        return cell;
+                        return Some(cell);
                     })
-                    .collect()
+                    .collect::<Option<Vec<_>>>()
             })
-            .collect();
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error)?;
 
-        Ok(Self { start, cells })
+        let mut map = Self { start: start.ok_or(Error)?, cells };
+        map.get_mut(map.start).pipe = map.start_pipe()?;
+        map.trace_loop().ok_or(Error)?;
+        Ok(map)
     }
 }
 
@@ -45,7 +60,7 @@ impl Map {
     fn get(&self, rc: (usize, usize)) -> &Cell { return &self.cells[rc.0][rc.1] }
     fn get_mut(&mut self, rc: (usize, usize)) -> &mut Cell { return &mut self.cells[rc.0][rc.1] }
 
-    fn direction_of(&self, dir: Direction, rc : (usize, usize)) -> (usize, usize) {
+    fn direction_of(&self, dir: Direction, rc : (usize, usize)) -> Option<(usize, usize)> {
         match dir {
             Direction::North => self.north_of(rc),
             Direction::East => self.east_of(rc),
@@ -54,10 +69,152 @@ impl Map {
         }
     }
 
-    fn east_of(&self, rc : (usize, usize)) -> (usize, usize) { return (rc.0, rc.1+1) }
-    fn west_of(&self, rc : (usize, usize)) -> (usize, usize) { return (rc.0, rc.1-1) }
-    fn north_of(&self, rc : (usize, usize)) -> (usize, usize) { return (rc.0-1, rc.1) }
-    fn south_of(&self, rc : (usize, usize)) -> (usize, usize) { return (rc.0+1, rc.1) }
+    // Neighbour lookups are None off the edge of the map.  Rows aren't guaranteed to be the
+    // same length, so east and south check against the row they land in.
+    fn east_of(&self, rc : (usize, usize)) -> Option<(usize, usize)> { return self.on_map((rc.0, rc.1+1)) }
+    fn west_of(&self, rc : (usize, usize)) -> Option<(usize, usize)> { return Some((rc.0, rc.1.checked_sub(1)?)) }
+    fn north_of(&self, rc : (usize, usize)) -> Option<(usize, usize)> { return Some((rc.0.checked_sub(1)?, rc.1)) }
+    fn south_of(&self, rc : (usize, usize)) -> Option<(usize, usize)> { return self.on_map((rc.0+1, rc.1)) }
+
+    fn on_map(&self, rc: (usize, usize)) -> Option<(usize, usize)> {
+        return self.cells.get(rc.0)?.get(rc.1).map(|_| rc);
+    }
+
+    // The 'S' hides a real pipe, which must be the one joining the two neighbours that lead
+    // back into the start.  Anything other than exactly two of those isn't a loop.
+    fn start_pipe(&self) -> Result<PipeDir, Error> {
+        return PipeDir::from_connections(
+            self.north_of(self.start).is_some_and(|rc| self.get(rc).goes_south()),
+            self.south_of(self.start).is_some_and(|rc| self.get(rc).goes_north()),
+            self.east_of(self.start).is_some_and(|rc| self.get(rc).goes_west()),
+            self.west_of(self.start).is_some_and(|rc| self.get(rc).goes_east()),
+        ).ok_or(Error);
+    }
+
+    // Cells the pipe at rc leads into.
+    fn neighbours(&self, rc: (usize, usize)) -> Vec<(usize, usize)> {
+        return self.get(rc).exits().into_iter().filter_map(|dir| self.direction_of(dir, rc)).collect();
+    }
+
+    // Set start_distance on every cell of the loop, the shorter way round from the start.
+    // Returns the distance to the farthest cell.
+    fn mark_distances(&mut self) -> u16 {
+        let cells = self.loop_cells();
+        for (i, rc) in cells.iter().enumerate() {
+            self.get_mut(*rc).start_distance = Some(i.min(cells.len() - i) as u16);
+        }
+        return (cells.len() / 2) as u16;
+    }
+
+    // The loop as an ordered list of cells, beginning with the start and following the first
+    // connected neighbour around until we come back to it.  Map::from_str has already checked
+    // that this works.
+    fn loop_cells(&self) -> Vec<(usize, usize)> {
+        return self.trace_loop().expect("Map::from_str checks the loop closes");
+    }
+
+    // As loop_cells, or None if the pipes lead off the edge of the map or into a pipe that
+    // doesn't join up with them before getting back to the start.
+    fn trace_loop(&self) -> Option<Vec<(usize, usize)>> {
+        let mut cells = vec![self.start().rc];
+        let mut prev_rc = self.start;
+        let mut rc = *self.neighbours(self.start).first()?;
+        while rc != self.start {
+            cells.push(rc);
+            let next_rc = self.direction_of(self.get(rc).traverse(prev_rc)?, rc)?;
+            prev_rc = rc;
+            rc = next_rc;
+        }
+        return Some(cells);
+    }
+
+    // Shoelace formula gives the area enclosed by the loop's cell centres, and Pick's theorem
+    // (A = i + b/2 - 1) turns that into the number of whole tiles strictly inside it.
+    fn enclosed_tiles(&self) -> usize {
+        let cells = self.loop_cells();
+        let twice_area: isize = cells
+            .iter()
+            .zip(cells.iter().cycle().skip(1))
+            .map(|(a, b)| a.1 as isize * b.0 as isize - b.1 as isize * a.0 as isize)
+            .sum();
+        return (twice_area.unsigned_abs() - cells.len()) / 2 + 1;
+    }
+
+    // Classify every tile by scanning each row and flipping inside/outside whenever we cross a
+    // loop pipe that goes north.  Counting only the northward half of the pipes means a run like
+    // L--7 flips once (it crosses the row) while L--J flips twice (it just grazes it).
+    fn regions(&self) -> Vec<Vec<Region>> {
+        let on_loop: HashSet<(usize, usize)> = self.loop_cells().into_iter().collect();
+        return self.cells.iter().map(|row| {
+            let mut inside = false;
+            row.iter().map(|cell| {
+                if !on_loop.contains(&cell.rc) {
+                    return if inside { Region::Inside } else { Region::Outside };
+                }
+                if cell.goes_north() { inside = !inside; }
+                return Region::Loop;
+            }).collect()
+        }).collect();
+    }
+
+    // Draw the loop with box-drawing characters and every other tile as I (inside) or O
+    // (outside).  With colour on, the inside is green, and stray pipes that aren't part of the
+    // loop are drawn dimmed rather than replaced by a letter.
+    fn render(&self, colour: bool) -> String {
+        let lines: Vec<String> = self.cells.iter().zip(self.regions()).map(|(row, regions)| {
+            row.iter().zip(regions).map(|(cell, region)| {
+                let stray = cell.pipe != PipeDir::None;
+                match (region, colour) {
+                    (Region::Loop, false) => cell.pipe.to_box_char().to_string(),
+                    (Region::Inside, false) => "I".to_string(),
+                    (Region::Outside, false) => "O".to_string(),
+                    (Region::Loop, true) => format!("\x1b[1m{}\x1b[0m", cell.pipe.to_box_char()),
+                    (Region::Inside, true) if stray => format!("\x1b[2;32m{}\x1b[0m", cell.pipe.to_box_char()),
+                    (Region::Inside, true) => "\x1b[1;32mI\x1b[0m".to_string(),
+                    (Region::Outside, true) if stray => format!("\x1b[2m{}\x1b[0m", cell.pipe.to_box_char()),
+                    (Region::Outside, true) => "\x1b[2mO\x1b[0m".to_string(),
+                }
+            }).collect()
+        }).collect();
+
+        return lines.join("\n");
+    }
+
+    // A heat map of the loop by distance from the start, with the pipe drawn through it and the
+    // inside of the loop shaded grey.  Distances come from mark_distances, so run that first.
+    fn to_picture(&self) -> Picture {
+        let max_distance = self.cells.iter().flatten().filter_map(|c| c.start_distance).max().unwrap_or(0).max(1);
+        let width = self.cells.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut picture = Picture::new(width * TILE_PX, self.cells.len() * TILE_PX, (255, 255, 255));
+
+        for (row, regions) in self.cells.iter().zip(self.regions()) {
+            for (cell, region) in row.iter().zip(regions) {
+                let (x, y) = (cell.rc.1 * TILE_PX, cell.rc.0 * TILE_PX);
+                match region {
+                    Region::Outside => {},
+                    Region::Inside => picture.rect(x, y, TILE_PX, TILE_PX, (210, 210, 210)),
+                    Region::Loop => {
+                        let colour = cell.start_distance
+                            .map_or((128, 128, 128), |d| heat_colour(d as f64 / max_distance as f64));
+                        picture.rect(x, y, TILE_PX, TILE_PX, colour);
+
+                        let centre = (x + TILE_PX / 2, y + TILE_PX / 2);
+                        for dir in cell.exits() {
+                            let edge = match dir {
+                                Direction::North => (centre.0, y),
+                                Direction::East => (x + TILE_PX - 1, centre.1),
+                                Direction::South => (centre.0, y + TILE_PX - 1),
+                                Direction::West => (x, centre.1),
+                            };
+                            picture.line(centre, edge, (0, 0, 0));
+                        }
+                    },
+                }
+            }
+        }
+
+        return picture;
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
@@ -68,35 +225,46 @@ pub struct Cell {
 }
 
 impl Cell {
-    fn from_char(rc: (usize, usize), ch: char) -> Cell {
-        return Cell {
+    // None if ch isn't a pipe, `S` or `.`.
+    fn from_char(rc: (usize, usize), ch: char) -> Option<Cell> {
+        return Some(Cell {
             rc,
             start_distance: if ch == 'S' { Some(0) } else { None },
-            pipe: PipeDir::from_char(ch),
-        };
+            pipe: PipeDir::from_char(ch)?,
+        });
     }
 
-    fn traverse(&self, from_rc: (usize, usize)) -> Direction {
+    // Which way the pipe leads on, coming in from from_rc, or None if it isn't connected that way.
+    fn traverse(&self, from_rc: (usize, usize)) -> Option<Direction> {
         let dy = self.rc.0 as isize - from_rc.0 as isize;
         let dx = self.rc.1 as isize - from_rc.1 as isize;
 
         match (dy, dx, &self.pipe) {
-            (0, 1, PipeDir::EastWest) => Direction::East,
-            (0, 1, PipeDir::NorthWest) => Direction::North,
-            (0, 1, PipeDir::SouthWest) => Direction::South,
-            (0, -1, PipeDir::EastWest) => Direction::West,
-            (0, -1, PipeDir::NorthEast) => Direction::North,
-            (0, -1, PipeDir::SouthEast) => Direction::South,
-            (1, 0, PipeDir::NorthSouth) => Direction::South,
-            (1, 0, PipeDir::NorthEast) => Direction::East,
-            (1, 0, PipeDir::NorthWest) => Direction::West,
-            (-1, 0, PipeDir::NorthSouth) => Direction::North,
-            (-1, 0, PipeDir::SouthEast) => Direction::East,
-            (-1, 0, PipeDir::SouthWest) => Direction::West,
-            _ => unreachable!(),
+            (0, 1, PipeDir::EastWest) => Some(Direction::East),
+            (0, 1, PipeDir::NorthWest) => Some(Direction::North),
+            (0, 1, PipeDir::SouthWest) => Some(Direction::South),
+            (0, -1, PipeDir::EastWest) => Some(Direction::West),
+            (0, -1, PipeDir::NorthEast) => Some(Direction::North),
+            (0, -1, PipeDir::SouthEast) => Some(Direction::South),
+            (1, 0, PipeDir::NorthSouth) => Some(Direction::South),
+            (1, 0, PipeDir::NorthEast) => Some(Direction::East),
+            (1, 0, PipeDir::NorthWest) => Some(Direction::West),
+            (-1, 0, PipeDir::NorthSouth) => Some(Direction::North),
+            (-1, 0, PipeDir::SouthEast) => Some(Direction::East),
+            (-1, 0, PipeDir::SouthWest) => Some(Direction::West),
+            _ => None,
         }
     }
 
+    fn exits(&self) -> Vec<Direction> {
+        let mut exits = vec![];
+        if self.goes_north() { exits.push(Direction::North); }
+        if self.goes_east() { exits.push(Direction::East); }
+        if self.goes_south() { exits.push(Direction::South); }
+        if self.goes_west() { exits.push(Direction::West); }
+        return exits;
+    }
+
     fn goes_north(&self) -> bool {
         return self.pipe == PipeDir::NorthSouth
             || self.pipe == PipeDir::NorthEast
@@ -131,91 +299,229 @@ enum PipeDir {
 }
 
 impl PipeDir {
-    fn from_char(ch: char) -> Self {
+    fn from_char(ch: char) -> Option<Self> {
         return match ch {
-            '-' => Self::EastWest,
-            '|' => Self::NorthSouth,
-            'L' => Self::NorthEast,
-            'J' => Self::NorthWest,
-            'F' => Self::SouthEast,
-            '7' => Self::SouthWest,
-            'S' | '.' => Self::None,
-            _ => unreachable!(),
+            '-' => Some(Self::EastWest),
+            '|' => Some(Self::NorthSouth),
+            'L' => Some(Self::NorthEast),
+            'J' => Some(Self::NorthWest),
+            'F' => Some(Self::SouthEast),
+            '7' => Some(Self::SouthWest),
+            'S' | '.' => Some(Self::None),
+            _ => None,
+        };
+    }
+
+    fn to_box_char(&self) -> char {
+        return match self {
+            Self::EastWest => '─',
+            Self::NorthSouth => '│',
+            Self::NorthEast => '└',
+            Self::NorthWest => '┘',
+            Self::SouthEast => '┌',
+            Self::SouthWest => '┐',
+            Self::None => ' ',
+        };
+    }
+
+    fn from_connections(north: bool, south: bool, east: bool, west: bool) -> Option<Self> {
+        return match (north, south, east, west) {
+            (true, true, false, false) => Some(Self::NorthSouth),
+            (false, false, true, true) => Some(Self::EastWest),
+            (true, false, true, false) => Some(Self::NorthEast),
+            (true, false, false, true) => Some(Self::NorthWest),
+            (false, true, true, false) => Some(Self::SouthEast),
+            (false, true, false, true) => Some(Self::SouthWest),
+            _ => None,
         };
     }
 }
 
-fn day10() {
+fn day10() -> io::Result<()> {
     let input = fs::read_to_string("input.txt").unwrap();
     let mut map: Map = input.parse().unwrap();
 
-    let mut prev_locations : Vec<(usize, usize)> = vec![];
-    let mut locations: Vec<(usize, usize)> = vec![];
-
-    if map.get(map.north_of(map.start)).goes_south() {
-        let cell = map.get_mut(map.north_of(map.start));
-        cell.start_distance = Some(1);
-        locations.push(cell.rc);
-        prev_locations.push(map.start);
-    }
-    if map.get(map.south_of(map.start)).goes_north() {
-        let cell = map.get_mut(map.south_of(map.start));
-        cell.start_distance = Some(1);
-        locations.push(cell.rc);
-        prev_locations.push(map.start);
-    }
-    if map.get(map.east_of(map.start)).goes_west() {
-        let cell = map.get_mut(map.east_of(map.start));
-        cell.start_distance = Some(1);
-        locations.push(cell.rc);
-        prev_locations.push(map.start);
-    }
-    if map.get(map.west_of(map.start)).goes_east() {
-        let cell = map.get_mut(map.west_of(map.start));
-        cell.start_distance = Some(1);
-        locations.push(cell.rc);
-        prev_locations.push(map.start);
-    }
-
-    let mut current_cost = 2u16;
-    loop {
-        let mut new_locations = vec![];
-        let mut new_prev_locations = vec![];
-        for (rc, prev_rc) in locations.iter().zip(prev_locations.iter()) {
-            let new_rc = map.direction_of(
-                map.get(*rc).traverse(*prev_rc),
-                *rc);
-
-            if map.get(new_rc).start_distance.is_none() {
-                map.get_mut(new_rc).start_distance = Some(current_cost);
-                new_prev_locations.push(*rc);
-                new_locations.push(new_rc);
-            }
-        }
-        if new_locations.is_empty() {
-            // Minus one because we *didn't* find (and therefore set cost on) any cells this round.
-            current_cost -= 1;
-            break;
-        }
-
-        current_cost += 1;
-        prev_locations = new_prev_locations;
-        locations = new_locations;
-    }
-    
-    println!("{:?}", current_cost);
+    println!("Part 1: {:?}", map.mark_distances());
+    println!("Part 2: {:?}", map.enclosed_tiles());
 
-    let printable : Vec<String> = map.cells.iter().map(|row| row.iter().map(
-        |x| if let Some(dist) = x.start_distance { char::from_digit((dist % 10) as u32, 10).unwrap()  } else { '.' }
-    ).collect() ).collect();
+    println!("{}", map.render(io::stdout().is_terminal()));
 
-    //println!("{:}", input);
-    println!("{}", printable.join("\n"));
+    // Optionally save the loop as an image, e.g. `cargo run -- loop.png`
+    if let Some(path) = env::args().nth(1) {
+        map.to_picture().save(&path)?;
+    }
 
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
-    day10();
+    day10()
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picture::hex;
+
+    #[test]
+    fn part1_tests() {
+        let mut map: Map = fs::read_to_string("test_input.txt").unwrap().parse().unwrap();
+        assert_eq!(map.mark_distances(), 8);
+
+        // Same loop without the padding column, so the start sits on the western edge.
+        let mut map: Map = ["..F7.", ".FJ|.", "SJ.L7", "|F--J", "LJ..."].join("\n").parse().unwrap();
+        assert_eq!(map.mark_distances(), 8);
+
+        // Start in the corner, with the loop running along the border.
+        let mut map: Map = ["S-7", "|.|", "L-J"].join("\n").parse().unwrap();
+        assert_eq!(map.start().pipe, PipeDir::SouthEast);
+        assert_eq!(map.mark_distances(), 4);
+        assert_eq!(map.enclosed_tiles(), 1);
+
+        let mut map: Map = ["F-7", "|.|", "L-S"].join("\n").parse().unwrap();
+        assert_eq!(map.start().pipe, PipeDir::NorthWest);
+        assert_eq!(map.mark_distances(), 4);
+    }
+
+    #[test]
+    fn start_pipe_tests() {
+        let map: Map = fs::read_to_string("test_input.txt").unwrap().parse().unwrap();
+        assert_eq!(map.start().pipe, PipeDir::SouthEast);
+
+        let map: Map = [".....", ".S-7.", ".|.|.", ".L-J.", "....."].join("\n").parse().unwrap();
+        assert_eq!(map.start().pipe, PipeDir::SouthEast);
+        assert!(map.start().goes_south() && map.start().goes_east());
+
+        // Three pipes lead into this start, so there's no telling which two make the loop.
+        assert!([".....", "-S-7.", ".|.|.", ".L-J.", "....."].join("\n").parse::<Map>().is_err());
+        // ... and here only one does.
+        assert!([".....", ".S-7.", "...|.", ".L-J.", "....."].join("\n").parse::<Map>().is_err());
+        assert!(["...", ".F.", "..."].join("\n").parse::<Map>().is_err());
+
+        // The start looks fine, but the loop doesn't close: it runs into a dot, into a pipe
+        // facing the wrong way, or off the edge of the map.
+        assert!(["S-7", "|.|", "L-."].join("\n").parse::<Map>().is_err());
+        assert!(["S-7", "|.|", "L-L"].join("\n").parse::<Map>().is_err());
+        assert!(["S-7", "|.|", "L--"].join("\n").parse::<Map>().is_err());
+
+        // Anything that isn't a pipe, S or a dot.
+        assert!(["S-7", "|X|", "L-J"].join("\n").parse::<Map>().is_err());
+        assert!(["S-7", "| |", "L-J"].join("\n").parse::<Map>().is_err());
+    }
+
+    #[test]
+    fn render_tests() {
+        let map: Map = fs::read_to_string("test_input.txt").unwrap().parse().unwrap();
+        assert_eq!(map.render(false), fs::read_to_string("test_render.txt").unwrap().trim_end());
+
+        let map: Map = [
+            "..........",
+            ".S------7.",
+            ".|F----7|.",
+            ".||OOOO||.",
+            ".||OOOO||.",
+            ".|L-7F-J|.",
+            ".|II||II|.",
+            ".L--JL--J.",
+            "..........",
+        ].join("\n").replace(['O', 'I'], ".").parse().unwrap();
+        assert_eq!(map.render(false), [
+            "OOOOOOOOOO",
+            "O┌──────┐O",
+            "O│┌────┐│O",
+            "O││OOOO││O",
+            "O││OOOO││O",
+            "O│└─┐┌─┘│O",
+            "O│II││II│O",
+            "O└──┘└──┘O",
+            "OOOOOOOOOO",
+        ].join("\n"));
+        assert_eq!(map.regions().concat().iter().filter(|r| **r == Region::Inside).count(), map.enclosed_tiles());
+
+        // Stray pipes are letters in plain text but dimmed pipes in colour.
+        let map: Map = ["F-7..", "|.|-.", "S-J.."].join("\n").parse().unwrap();
+        assert_eq!(map.render(false), ["┌─┐OO", "│I│OO", "└─┘OO"].join("\n"));
+        assert!(map.render(true).contains("\x1b[2m─\x1b[0m"));
+    }
+
+    #[test]
+    fn picture_tests() {
+        let mut map: Map = ["S-7", "|.|", "L-J"].join("\n").parse().unwrap();
+        map.mark_distances();
+        let picture = map.to_picture();
+        assert_eq!((picture.width, picture.height), (3 * TILE_PX, 3 * TILE_PX));
+
+        let svg = picture.to_svg();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // Start and farthest tiles are the two ends of the heat scale, the middle is shaded inside.
+        assert!(svg.contains(&format!("fill=\"{}\"", hex(&heat_colour(0.0)))));
+        assert!(svg.contains(&format!("fill=\"{}\"", hex(&heat_colour(1.0)))));
+        assert!(svg.contains(&format!("<rect x=\"{0}\" y=\"{0}\" width=\"{1}\" height=\"{1}\" fill=\"#d2d2d2\"/>", TILE_PX, TILE_PX)));
+
+        let pixel = |x, y| picture.pixel(x, y);
+        assert_eq!(pixel(0, 0), heat_colour(0.0));
+        assert_eq!(pixel(TILE_PX / 2, TILE_PX / 2), (0, 0, 0));
+        assert_eq!(pixel(TILE_PX + 1, TILE_PX + 1), (210, 210, 210));
+
+        let png = picture.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn part2_tests() {
+        let map: Map = [
+            "...........",
+            ".S-------7.",
+            ".|F-----7|.",
+            ".||.....||.",
+            ".||.....||.",
+            ".|L-7.F-J|.",
+            ".|..|.|..|.",
+            ".L--J.L--J.",
+            "...........",
+        ].join("\n").parse().unwrap();
+        assert_eq!(map.enclosed_tiles(), 4);
+
+        // Squeezing between pipes doesn't connect the inside to the outside.
+        let map: Map = [
+            "..........",
+            ".S------7.",
+            ".|F----7|.",
+            ".||....||.",
+            ".||....||.",
+            ".|L-7F-J|.",
+            ".|..||..|.",
+            ".L--JL--J.",
+            "..........",
+        ].join("\n").parse().unwrap();
+        assert_eq!(map.enclosed_tiles(), 4);
+
+        let map: Map = [
+            ".F----7F7F7F7F-7....",
+            ".|F--7||||||||FJ....",
+            ".||.FJ||||||||L7....",
+            "FJL7L7LJLJ||LJ.L-7..",
+            "L--J.L7...LJS7F-7L7.",
+            "....F-J..F7FJ|L7L7L7",
+            "....L7.F7||L7|.L7L7|",
+            ".....|FJLJ|FJ|F7|.LJ",
+            "....FJL-7.||.||||...",
+            "....L---J.LJ.LJLJ...",
+        ].join("\n").parse().unwrap();
+        assert_eq!(map.enclosed_tiles(), 8);
+
+        let map: Map = [
+            "FF7FSF7F7F7F7F7F---7",
+            "L|LJ||||||||||||F--J",
+            "FL-7LJLJ||||||LJL-77",
+            "F--JF--7||LJLJ7F7FJ-",
+            "L---JF-JLJ.||-FJLJJ7",
+            "|F|F-JF---7F7-L7L|7|",
+            "|FFJF7L7F-JF7|JL---7",
+            "7-L-JL7||F7|L7F-7F7|",
+            "L.L7LFJ|||||FJL7||LJ",
+            "L7JLJL-JLJLJL--JLJ.L",
+        ].join("\n").parse().unwrap();
+        assert_eq!(map.enclosed_tiles(), 10);
+    }
 }
@@ -1,64 +1,277 @@
 use itertools::Itertools;
-use std::collections::HashSet;
-use std::{fs, io};
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use std::{fs, io, iter};
 
-fn manhattan_distance(a: &(usize, usize), b: &(usize, usize)) -> usize {
-    (a.0.max(b.0) - a.0.min(b.0)) + (a.1.max(b.1) - a.1.min(b.1))
-}
+// Expansion factors up to this are drawn literally by `expand`, larger ones compressed.
+const LITERAL_FACTOR_LIMIT: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct Error;
 
-fn empty_rows_crossed(
-    a: &(usize, usize),
-    b: &(usize, usize),
-    empty_rows: &HashSet<usize>,
-) -> usize {
-    HashSet::from_iter(a.0.min(b.0)..a.0.max(b.0))
-        .intersection(empty_rows)
-        .count()
+#[derive(Debug, Clone)]
+pub struct Universe {
+    galaxies: Vec<(usize, usize)>, // row, col
+    n_rows: usize,
+    n_cols: usize,
+    empty_rows: HashSet<usize>,
+    empty_cols: HashSet<usize>,
 }
 
-fn empty_cols_crossed(
-    a: &(usize, usize),
-    b: &(usize, usize),
-    empty_cols: &HashSet<usize>,
-) -> usize {
-    HashSet::from_iter(a.1.min(b.1)..a.1.max(b.1))
-        .intersection(empty_cols)
-        .count()
+impl FromStr for Universe {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut galaxies: Vec<(usize, usize)> = vec![];
+        let n_rows = s.lines().count();
+        let n_cols = s.lines().next().ok_or(Error)?.chars().count();
+        let mut empty_rows = HashSet::from_iter(0..n_rows);
+        let mut empty_cols = HashSet::from_iter(0..n_cols);
+
+        for (row, line) in s.lines().enumerate() {
+            if line.chars().count() != n_cols {
+                return Err(Error);
+            }
+            for (col, ch) in line.chars().enumerate() {
+                match ch {
+                    '#' => {
+                        empty_rows.remove(&row);
+                        empty_cols.remove(&col);
+                        galaxies.push((row, col));
+                    }
+                    '.' => {}
+                    _ => return Err(Error),
+                }
+            }
+        }
+
+        Ok(Universe { galaxies, n_rows, n_cols, empty_rows, empty_cols })
+    }
 }
 
-fn day11() {
-    let input = fs::read_to_string("input.txt").unwrap();
-
-    let mut galaxies: Vec<(usize, usize)> = vec![];
-    let n_rows = input.lines().count();
-    let n_cols = input.lines().next().unwrap().len();
-    let mut empty_rows = HashSet::from_iter(0..n_rows);
-    let mut empty_cols = HashSet::from_iter(0..n_cols);
-
-    for (row, line) in input.lines().enumerate() {
-        for (col, ch) in line.chars().enumerate() {
-            if ch == '#' {
-                empty_rows.remove(&row);
-                empty_cols.remove(&col);
-                galaxies.push((row, col));
+impl Universe {
+    // Galaxy positions after every empty row and column has been replaced by `factor` of them.
+    // Every query goes through here, so they all give Error for a factor of 0, or one so big
+    // that the last row plus the last column doesn't fit in an isize.  Below that, no distance
+    // (or row + col, or row - col) can overflow.
+    fn expanded(&self, factor: usize) -> Result<Vec<(usize, usize)>, Error> {
+        let row_shift = expansion_shifts(self.n_rows, &self.empty_rows, factor)?;
+        let col_shift = expansion_shifts(self.n_cols, &self.empty_cols, factor)?;
+        let last_row = self.n_rows.saturating_sub(1).checked_add(*row_shift.last().unwrap_or(&0)).ok_or(Error)?;
+        let last_col = self.n_cols.saturating_sub(1).checked_add(*col_shift.last().unwrap_or(&0)).ok_or(Error)?;
+        if last_row.checked_add(last_col).is_none_or(|last| last > isize::MAX as usize) {
+            return Err(Error);
+        }
+        Ok(self.galaxies
+            .iter()
+            .map(|(r, c)| (r + row_shift[*r], c + col_shift[*c]))
+            .collect())
+    }
+
+    // Sum of the distances between every pair of galaxies.  Manhattan distance splits into
+    // independent row and column sums, and each of those is a sort and a prefix sum.
+    // Error if the total doesn't fit in a usize.
+    fn total_distance(&self, factor: usize) -> Result<usize, Error> {
+        let (rows, cols): (Vec<usize>, Vec<usize>) = self.expanded(factor)?.into_iter().unzip();
+        sum_of_differences(rows)?.checked_add(sum_of_differences(cols)?).ok_or(Error)
+    }
+
+    // Galaxies are numbered from 1 in reading order, as in the puzzle text.  None if there's no
+    // such galaxy.
+    fn distance(&self, a: usize, b: usize, factor: usize) -> Result<Option<usize>, Error> {
+        let expanded = self.expanded(factor)?;
+        let galaxy = |id: usize| id.checked_sub(1).and_then(|i| expanded.get(i));
+        Ok(galaxy(a).zip(galaxy(b)).map(|(a, b)| manhattan_distance(a, b)))
+    }
+
+    // The k galaxies closest to galaxy `id`, as (id, distance), closest first.
+    fn nearest(&self, id: usize, k: usize, factor: usize) -> Result<Vec<(usize, usize)>, Error> {
+        let expanded = self.expanded(factor)?;
+        let Some(from) = id.checked_sub(1).and_then(|i| expanded.get(i)) else { return Ok(vec![]) };
+        let mut distances: Vec<(usize, usize)> = expanded
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i + 1 != id)
+            .map(|(i, to)| (i + 1, manhattan_distance(from, to)))
+            .collect();
+        distances.sort_by_key(|(other, dist)| (*dist, *other));
+        distances.truncate(k);
+        Ok(distances)
+    }
+
+    // The two galaxies furthest apart, as (id, id, distance).
+    fn farthest_pair(&self, factor: usize) -> Result<Option<(usize, usize, usize)>, Error> {
+        Ok(farthest_pair(&self.expanded(factor)?))
+    }
+
+    // How many pairs of galaxies fall in each `bucket`-wide range of distances, keyed by the
+    // start of the range, or Error if `bucket` is 0.  Every pair is visited, so this is
+    // quadratic in the galaxy count.
+    fn distance_histogram(&self, factor: usize, bucket: usize) -> Result<BTreeMap<usize, usize>, Error> {
+        if bucket == 0 {
+            return Err(Error);
+        }
+        let mut histogram = BTreeMap::new();
+        for pair in self.expanded(factor)?.iter().combinations(2) {
+            let dist = manhattan_distance(pair[0], pair[1]);
+            *histogram.entry(dist / bucket * bucket).or_insert(0) += 1;
+        }
+        Ok(histogram)
+    }
+
+    // The image after expansion, with galaxies numbered as in the puzzle text.  Every tile is as
+    // wide as the largest number, so with ten or more galaxies 7 is drawn as .7 (or ..7), and
+    // empty tiles as .. (or ...).  Small factors are drawn literally.  Past that, each run of
+    // empty rows or columns is drawn once, as a line of - or |, and annotated with how wide it
+    // has become.
+    fn expand(&self, factor: usize) -> Result<String, Error> {
+        match factor {
+            0 => Err(Error),
+            1..=LITERAL_FACTOR_LIMIT => Ok(self.expand_literal(factor)),
+            _ => Ok(self.expand_compressed(factor)),
+        }
+    }
+
+    fn tile_width(&self) -> usize {
+        self.galaxies.len().max(1).to_string().len()
+    }
+
+    fn image(&self) -> Vec<Vec<String>> {
+        let width = self.tile_width();
+        let mut image = vec![vec![".".repeat(width); self.n_cols]; self.n_rows];
+        for (i, (r, c)) in self.galaxies.iter().enumerate() {
+            image[*r][*c] = format!("{:.>w$}", i + 1, w = width);
+        }
+        image
+    }
+
+    fn expand_literal(&self, factor: usize) -> String {
+        let mut lines = vec![];
+        for (r, row) in self.image().iter().enumerate() {
+            let line: String = row
+                .iter()
+                .enumerate()
+                .flat_map(|(c, tile)| iter::repeat_n(tile.as_str(), if self.empty_cols.contains(&c) { factor } else { 1 }))
+                .collect();
+            lines.extend(iter::repeat_n(line, if self.empty_rows.contains(&r) { factor } else { 1 }));
+        }
+        lines.join("\n")
+    }
+
+    fn expand_compressed(&self, factor: usize) -> String {
+        // The columns to draw: Some(col) for a real one, None for a run of empty ones.
+        let mut columns: Vec<Option<usize>> = vec![];
+        let mut col_gaps: Vec<usize> = vec![];
+        for c in 0..self.n_cols {
+            if !self.empty_cols.contains(&c) {
+                columns.push(Some(c));
+            } else if let (Some(None), Some(width)) = (columns.last(), col_gaps.last_mut()) {
+                *width += factor;
+            } else {
+                columns.push(None);
+                col_gaps.push(factor);
+            }
+        }
+
+        let width = self.tile_width();
+        let gap_line = |rows: usize| {
+            let line: String = columns.iter().map(|c| if c.is_some() { "-".repeat(width) } else { "+".to_string() }).collect();
+            format!("{}  {} rows", line, rows)
+        };
+
+        let mut lines = vec![];
+        let mut gap_rows = 0;
+        for (r, row) in self.image().iter().enumerate() {
+            if self.empty_rows.contains(&r) {
+                gap_rows += factor;
+                continue;
+            }
+            if gap_rows > 0 {
+                lines.push(gap_line(gap_rows));
+                gap_rows = 0;
             }
+            lines.push(columns.iter().map(|c| c.map_or("|", |c| row[c].as_str())).collect());
         }
+        if gap_rows > 0 {
+            lines.push(gap_line(gap_rows));
+        }
+        if !col_gaps.is_empty() {
+            lines.push(format!("| columns: {}", col_gaps.iter().join(", ")));
+        }
+
+        lines.join("\n")
     }
+}
 
-    let mut part1: usize = 0;
-    let mut part2: usize = 0;
+// As Universe::farthest_pair, for galaxies already expanded.  Manhattan distance is the larger
+// of the spreads along the two diagonals, so only the extremes of row + col and row - col need
+// looking at.
+fn farthest_pair(expanded: &[(usize, usize)]) -> Option<(usize, usize, usize)> {
+    if expanded.len() < 2 {
+        return None;
+    }
+    let extremes = |key: &dyn Fn(&(usize, usize)) -> isize| {
+        let (min, max) = (0..expanded.len()).minmax_by_key(|i| key(&expanded[*i])).into_option()?;
+        Some((min + 1, max + 1, key(&expanded[max]).abs_diff(key(&expanded[min]))))
+    };
+    let sum = extremes(&|(r, c)| (r + c) as isize)?;
+    let difference = extremes(&|(r, c)| *r as isize - *c as isize)?;
+    let (a, b, dist) = if difference.2 > sum.2 { difference } else { sum };
+    Some((a.min(b), a.max(b), dist))
+}
 
-    for pair in galaxies.iter().combinations(2) {
-        let a = pair.first().unwrap();
-        let b = pair.last().unwrap();
-        let dist = manhattan_distance(a, b);
-        let rows = empty_rows_crossed(a, b, &empty_rows);
-        let cols = empty_cols_crossed(a, b, &empty_cols);
-        part1 += dist + rows + cols;
-        part2 += dist + 999999 * rows + 999999 * cols;
+fn manhattan_distance(a: &(usize, usize), b: &(usize, usize)) -> usize {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1)
+}
+
+// How far each index moves when every empty index before it grows to `factor` wide, or Error
+// if `factor` is 0 (empty rows can grow, or stay as they are, but not vanish) or the shifts
+// overflow.
+fn expansion_shifts(len: usize, empty: &HashSet<usize>, factor: usize) -> Result<Vec<usize>, Error> {
+    let growth = factor.checked_sub(1).ok_or(Error)?;
+    let mut shift: usize = 0;
+    (0..len)
+        .map(|i| {
+            let this_shift = shift;
+            if empty.contains(&i) {
+                shift = shift.checked_add(growth).ok_or(Error)?;
+            }
+            Ok(this_shift)
+        })
+        .collect()
+}
+
+// Sum of |a - b| over every pair of values.  Once sorted, the i'th value is larger than the i
+// values before it, so it contributes i * value minus the sum of those.  Error if the sum
+// overflows.
+fn sum_of_differences(mut values: Vec<usize>) -> Result<usize, Error> {
+    values.sort_unstable();
+    let mut preceding: usize = 0;
+    let mut total: usize = 0;
+    for (i, v) in values.iter().enumerate() {
+        let contribution = i.checked_mul(*v).ok_or(Error)? - preceding;
+        preceding = preceding.checked_add(*v).ok_or(Error)?;
+        total = total.checked_add(contribution).ok_or(Error)?;
+    }
+    Ok(total)
+}
+
+fn day11() {
+    let universe: Universe = fs::read_to_string("input.txt").unwrap().parse().unwrap();
+
+    println!("TOTAL: {:?}", universe.total_distance(2).unwrap()); // 9742154
+    println!("TOTAL: {:?}", universe.total_distance(1_000_000).unwrap()); // 411142919886
+
+    if let Some((a, b, dist)) = universe.farthest_pair(2).unwrap() {
+        println!("Farthest apart: {} and {} at {}", a, b, dist);
+        println!("Closest to {}: {:?}", a, universe.nearest(a, 3, 2).unwrap());
+        println!("Check: {:?}", universe.distance(a, b, 2).unwrap());
     }
-    println!("TOTAL: {:?}", part1); // 9331020
-    println!("TOTAL: {:?}", part2); // 411142919886
+    for (start, count) in universe.distance_histogram(2, 50).unwrap() {
+        println!("{:>4}-{:<4} {}", start, start + 49, count);
+    }
+
+    println!("{}", universe.expand(1_000_000).unwrap());
 }
 
 fn main() -> io::Result<()> {
@@ -66,3 +279,198 @@ fn main() -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn empty_rows_crossed(
+        a: &(usize, usize),
+        b: &(usize, usize),
+        empty_rows: &HashSet<usize>,
+    ) -> usize {
+        HashSet::from_iter(a.0.min(b.0)..a.0.max(b.0))
+            .intersection(empty_rows)
+            .count()
+    }
+
+    fn empty_cols_crossed(
+        a: &(usize, usize),
+        b: &(usize, usize),
+        empty_cols: &HashSet<usize>,
+    ) -> usize {
+        HashSet::from_iter(a.1.min(b.1)..a.1.max(b.1))
+            .intersection(empty_cols)
+            .count()
+    }
+
+    // The original pair-by-pair count, kept as a reference for the prefix sum version.
+    fn total_distance_pairwise(universe: &Universe, factor: usize) -> usize {
+        let mut total = 0;
+        for pair in universe.galaxies.iter().combinations(2) {
+            let a = pair.first().unwrap();
+            let b = pair.last().unwrap();
+            let rows = empty_rows_crossed(a, b, &universe.empty_rows);
+            let cols = empty_cols_crossed(a, b, &universe.empty_cols);
+            total += manhattan_distance(a, b) + (factor - 1) * (rows + cols);
+        }
+        total
+    }
+
+    #[test]
+    fn example_tests() {
+        let universe: Universe = fs::read_to_string("test_input.txt").unwrap().parse().unwrap();
+        assert_eq!(universe.total_distance(2).unwrap(), 374);
+        assert_eq!(universe.total_distance(10).unwrap(), 1030);
+        assert_eq!(universe.total_distance(100).unwrap(), 8410);
+        assert_eq!(universe.total_distance(1).unwrap(), 292);
+        assert_eq!(universe.expanded(2).unwrap()[0], (0, 4));
+
+        // A factor of 0 would make empty rows vanish, which isn't expansion.
+        assert!(universe.total_distance(0).is_err());
+        assert!(universe.distance(1, 2, 0).is_err());
+        assert!(universe.nearest(1, 2, 0).is_err());
+        assert!(universe.farthest_pair(0).is_err());
+        assert!(universe.distance_histogram(0, 5).is_err());
+        assert!(universe.distance_histogram(2, 0).is_err());
+        assert!(universe.expand(0).is_err());
+
+        // Factors too big to add up are errors too, as are ragged images.
+        assert!(universe.total_distance(usize::MAX).is_err());
+        assert!(universe.total_distance(usize::MAX / 4).is_err());
+        assert!(universe.distance(1, 2, usize::MAX).is_err());
+        let tall: Universe = ["#", ".", "#"].join("\n").parse().unwrap();
+        assert_eq!(tall.total_distance(1 << 40).unwrap(), (1 << 40) + 1);
+        assert!(tall.total_distance(usize::MAX).is_err());
+        assert!("#.\n..#".parse::<Universe>().is_err());
+        assert!("#..\n.#".parse::<Universe>().is_err());
+        assert_eq!("\n".parse::<Universe>().unwrap().total_distance(2).unwrap(), 0);
+    }
+
+    #[test]
+    fn expand_tests() {
+        let universe: Universe = fs::read_to_string("test_input.txt").unwrap().parse().unwrap();
+        assert_eq!(universe.expand(1).unwrap(), [
+            "...1......",
+            ".......2..",
+            "3.........",
+            "..........",
+            "......4...",
+            ".5........",
+            ".........6",
+            "..........",
+            ".......7..",
+            "8...9.....",
+        ].join("\n"));
+        assert_eq!(universe.expand(2).unwrap(), [
+            "....1........",
+            ".........2...",
+            "3............",
+            ".............",
+            ".............",
+            "........4....",
+            ".5...........",
+            "............6",
+            ".............",
+            ".............",
+            ".........7...",
+            "8....9.......",
+        ].join("\n"));
+        assert_eq!(universe.expand(1_000_000).unwrap(), [
+            "..|1.|..|.",
+            "..|..|.2|.",
+            "3.|..|..|.",
+            "--+--+--+-  1000000 rows",
+            "..|..|4.|.",
+            ".5|..|..|.",
+            "..|..|..|6",
+            "--+--+--+-  1000000 rows",
+            "..|..|.7|.",
+            "8.|.9|..|.",
+            "| columns: 1000000, 1000000, 1000000",
+        ].join("\n"));
+
+        // Neighbouring gaps collapse into one.
+        let universe: Universe = ["#...#", ".....", ".....", "....#"].join("\n").parse().unwrap();
+        assert_eq!(universe.expand(100).unwrap(), ["1|2", "-+-  200 rows", ".|3", "| columns: 300"].join("\n"));
+
+        // With ten or more galaxies, every tile is two wide.
+        let universe: Universe = ["#.#.#", ".....", "#.#.#", "##.##"].join("\n").parse().unwrap();
+        assert_eq!(universe.expand(2).unwrap(), [
+            ".1...2...3",
+            "..........",
+            "..........",
+            ".4...5...6",
+            ".7.8...910",
+        ].join("\n"));
+        let mut image = vec!["#".repeat(12)];
+        image.push(".".repeat(12));
+        let universe: Universe = image.join("\n").parse().unwrap();
+        assert_eq!(universe.expand(1_000).unwrap(), [".1.2.3.4.5.6.7.8.9101112", "------------------------  1000 rows"].join("\n"));
+    }
+
+    #[test]
+    fn query_tests() {
+        let universe: Universe = fs::read_to_string("test_input.txt").unwrap().parse().unwrap();
+        assert_eq!(universe.distance(5, 9, 2).unwrap(), Some(9));
+        assert_eq!(universe.distance(1, 7, 2).unwrap(), Some(15));
+        assert_eq!(universe.distance(3, 6, 2).unwrap(), Some(17));
+        assert_eq!(universe.distance(8, 9, 2).unwrap(), Some(5));
+        assert_eq!(universe.distance(0, 9, 2).unwrap(), None);
+        assert_eq!(universe.distance(1, 10, 2).unwrap(), None);
+
+        assert_eq!(universe.nearest(8, 2, 2).unwrap(), vec![(9, 5), (5, 6)]);
+        assert_eq!(universe.nearest(8, 100, 2).unwrap().len(), 8);
+        assert_eq!(universe.nearest(10, 2, 2).unwrap(), vec![]);
+
+        let histogram = universe.distance_histogram(2, 5).unwrap();
+        assert_eq!(histogram.values().sum::<usize>(), 36);
+        assert_eq!(histogram.keys().collect::<Vec<_>>(), vec![&5, &10, &15]);
+        let exact = universe.distance_histogram(2, 1).unwrap();
+        assert_eq!(exact.iter().map(|(dist, count)| dist * count).sum::<usize>(), 374);
+    }
+
+    // Up to `size` rows and columns, each tile a galaxy with probability `density`.
+    fn random_universe(rng: &mut StdRng, size: usize, density: f64) -> Universe {
+        let (rows, cols) = (rng.gen_range(1..size), rng.gen_range(1..size));
+        let image: Vec<String> = (0..rows)
+            .map(|_| (0..cols).map(|_| if rng.gen_bool(density) { '#' } else { '.' }).collect())
+            .collect();
+        image.join("\n").parse().unwrap()
+    }
+
+    #[test]
+    fn farthest_pair_matches_pairwise_on_random_universes() {
+        let mut rng = StdRng::seed_from_u64(32);
+        for _ in 0..50 {
+            let universe = random_universe(&mut rng, 20, 0.1);
+
+            for factor in [1, 2, 1_000] {
+                let expected = (1..=universe.galaxies.len())
+                    .tuple_combinations()
+                    .map(|(a, b)| universe.distance(a, b, factor).unwrap().unwrap())
+                    .max();
+                let farthest = universe.farthest_pair(factor).unwrap();
+                assert_eq!(farthest.map(|f| f.2), expected);
+                if let Some((a, b, dist)) = farthest {
+                    assert_eq!(universe.distance(a, b, factor).unwrap(), Some(dist));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_pairwise_on_random_universes() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..50 {
+            let density = rng.gen_range(0.0..0.3);
+            let universe = random_universe(&mut rng, 30, density);
+
+            for factor in [1, 2, 10, 1_000_000] {
+                assert_eq!(universe.total_distance(factor).unwrap(), total_distance_pairwise(&universe, factor));
+            }
+        }
+    }
+}
@@ -1,5 +1,16 @@
-use std::collections::HashSet;
-use std::{fs, io};
+use picture::{heat_colour, line_pixels, Colour, Picture};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use std::{env, fs, io, thread};
+
+// Side length of a tile in exported images, in pixels.
+const TILE_PX: usize = 9;
+
+// Side length of a tile in animated GIFs, in pixels.
+const GIF_TILE_PX: usize = 3;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum NESW {
@@ -9,15 +20,164 @@ pub enum NESW {
     West,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum TileType {
-    Empty,
-    MirrorSWNE,
-    MirrorNWSE,
-    SplitVert,
-    SplitHoriz,
+impl NESW {
+    // Each direction's bit in a Contraption's per-tile mask.
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            NESW::North => "north",
+            NESW::East => "east",
+            NESW::South => "south",
+            NESW::West => "west",
+        }
+    }
 }
 
+// The standard elements, one per line: the character, then the directions light leaves in
+// when it arrives travelling north, east, south and west.  `x` means it's absorbed.
+const STANDARD_ELEMENTS: &str = "\
+. N E S W
+/ E N W S
+\\ W S E N
+| N NS S NS
+- EW E EW W";
+
+#[derive(Debug, Clone)]
+pub struct Error;
+
+// An optical element: the character it's drawn with, and the directions light leaves it in for
+// each direction light can arrive travelling in (indexed by NESW).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    ch: char,
+    outputs: [Vec<NESW>; 4],
+}
+
+impl Element {
+    // Light goes straight through whichever way it's travelling.
+    fn is_transparent(&self) -> bool {
+        [NESW::North, NESW::East, NESW::South, NESW::West].iter().all(|d| self.outputs[*d as usize] == [*d])
+    }
+
+    // What to draw for the element, as lines between points on a 3x3 lattice over the tile
+    // (0 is the top or left edge, 1 the middle and 2 the bottom or right).  Elements that act
+    // like the standard ones look like them, whatever character they're given; anything else
+    // gets a line from the middle out to each side light can leave through, or an X if it only
+    // absorbs.
+    fn glyph(&self) -> Vec<((usize, usize), (usize, usize))> {
+        use NESW::*;
+        let outputs = self.outputs.clone().map(|mut dirs| {
+            dirs.sort();
+            dirs
+        });
+        match outputs.each_ref().map(|dirs| dirs.as_slice()) {
+            _ if self.is_transparent() => vec![],
+            [[East], [North], [West], [South]] => vec![((0, 2), (2, 0))],
+            [[West], [South], [East], [North]] => vec![((0, 0), (2, 2))],
+            [[North], [North, South], [South], [North, South]] => vec![((1, 0), (1, 2))],
+            [[East, West], [East], [East, West], [West]] => vec![((0, 1), (2, 1))],
+            _ => {
+                let leaving: HashSet<&NESW> = self.outputs.iter().flatten().collect();
+                if leaving.is_empty() {
+                    return vec![((0, 0), (2, 2)), ((0, 2), (2, 0))];
+                }
+                let mut lines: Vec<((usize, usize), (usize, usize))> = leaving
+                    .into_iter()
+                    .map(|dir| match dir {
+                        North => ((1, 1), (1, 0)),
+                        East => ((1, 1), (2, 1)),
+                        South => ((1, 1), (1, 2)),
+                        West => ((1, 1), (0, 1)),
+                    })
+                    .collect();
+                lines.sort();
+                lines
+            }
+        }
+    }
+
+    // The glyph's lines in pixels, for a tile `size` pixels across with its top left at (x, y).
+    fn glyph_lines(&self, x: usize, y: usize, size: usize) -> Vec<((usize, usize), (usize, usize))> {
+        let scale = |(gx, gy): (usize, usize)| (x + gx * (size - 1) / 2, y + gy * (size - 1) / 2);
+        self.glyph().into_iter().map(|(a, b)| (scale(a), scale(b))).collect()
+    }
+}
+
+// The elements a contraption can be built from.  Tiles refer to theirs by its index here.
+#[derive(Debug, Clone)]
+pub struct ElementSet {
+    elements: Vec<Element>,
+}
+
+impl ElementSet {
+    fn standard() -> Self {
+        Self::from_table(STANDARD_ELEMENTS).expect("the standard elements parse")
+    }
+
+    // Reads a table laid out like STANDARD_ELEMENTS.
+    fn from_table(table: &str) -> Result<Self, Error> {
+        let mut set = ElementSet { elements: vec![] };
+        set.extend(table)?;
+        Ok(set)
+    }
+
+    // Adds the elements from a table laid out like STANDARD_ELEMENTS.  A character that's
+    // already defined is redefined.
+    fn extend(&mut self, table: &str) -> Result<(), Error> {
+        for line in table.lines().filter(|l| !l.trim().is_empty()) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [ch, n, e, s, w] = fields[..] else { return Err(Error) };
+            let mut chars = ch.chars();
+            let (Some(ch), None) = (chars.next(), chars.next()) else { return Err(Error) };
+
+            let mut outputs: [Vec<NESW>; 4] = Default::default();
+            for (i, field) in [n, e, s, w].iter().enumerate() {
+                if *field == "x" {
+                    continue;
+                }
+                for dir in field.chars() {
+                    outputs[i].push(match dir {
+                        'N' => NESW::North,
+                        'E' => NESW::East,
+                        'S' => NESW::South,
+                        'W' => NESW::West,
+                        _ => return Err(Error),
+                    });
+                }
+            }
+
+            let element = Element { ch, outputs };
+            match self.elements.iter().position(|e| e.ch == ch) {
+                Some(i) => self.elements[i] = element,
+                None if self.elements.len() <= u8::MAX as usize => self.elements.push(element),
+                None => return Err(Error),
+            }
+        }
+        Ok(())
+    }
+
+    // Error if no element is drawn with ch.
+    fn tile_type(&self, ch: char) -> Result<TileType, Error> {
+        let index = self.elements.iter().position(|e| e.ch == ch).ok_or(Error)?;
+        Ok(TileType(index as u8))
+    }
+
+    fn get(&self, tile_type: TileType) -> &Element {
+        &self.elements[tile_type.0 as usize]
+    }
+
+    fn outputs(&self, tile_type: TileType, light_dir: &NESW) -> &[NESW] {
+        &self.get(tile_type).outputs[*light_dir as usize]
+    }
+}
+
+// An index into an ElementSet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TileType(u8);
+
 #[derive(Debug, Clone)]
 pub struct Tile {
     tile_type: TileType,
@@ -25,108 +185,328 @@ pub struct Tile {
 }
 
 impl Tile {
-    fn from_char(ch: &char) -> Tile {
-        Tile {
-            tile_type: match ch {
-                '.' => TileType::Empty,
-                '/' => TileType::MirrorSWNE,
-                '\\' => TileType::MirrorNWSE,
-                '|' => TileType::SplitVert,
-                '-' => TileType::SplitHoriz,
-                _ => unreachable!(),
-            },
+    fn from_char(ch: &char, elements: &ElementSet) -> Result<Tile, Error> {
+        Ok(Tile {
+            tile_type: elements.tile_type(*ch)?,
             active_inputs: HashSet::new(),
-        }
+        })
     }
 
-    fn to_char(&self) -> char {
-        match self.tile_type {
-            TileType::Empty => match self.active_inputs.len() {
-                0 => '.',
-                1 => match self.active_inputs.iter().next().unwrap() {
-                    NESW::North => '^',
-                    NESW::East => '>',
-                    NESW::South => 'v',
-                    NESW::West => '<',
-                },
-                2 => '2',
-                3 => '3',
-                4 => '4',
-                _ => '?',
+    fn to_char(&self, elements: &ElementSet) -> char {
+        let element = elements.get(self.tile_type);
+        if !element.is_transparent() {
+            return element.ch;
+        }
+        match self.active_inputs.len() {
+            0 => element.ch,
+            1 => match self.active_inputs.iter().next().unwrap() {
+                NESW::North => '^',
+                NESW::East => '>',
+                NESW::South => 'v',
+                NESW::West => '<',
             },
-            TileType::MirrorSWNE => '/',
-            TileType::MirrorNWSE => '\\',
-            TileType::SplitVert => '|',
-            TileType::SplitHoriz => '-',
+            2 => '2',
+            3 => '3',
+            4 => '4',
+            _ => '?',
         }
     }
 
-    fn activate(&mut self, light_dir: &NESW) -> Vec<NESW> {
+    fn activate<'a>(&mut self, light_dir: &NESW, elements: &'a ElementSet) -> &'a [NESW] {
         // Store input light direction
         self.active_inputs.insert(*light_dir);
 
         // Return the output directions
-        match self.tile_type {
-            TileType::Empty => match light_dir {
-                NESW::North => vec![NESW::North],
-                NESW::East => vec![NESW::East],
-                NESW::South => vec![NESW::South],
-                NESW::West => vec![NESW::West],
-            },
+        elements.outputs(self.tile_type, light_dir)
+    }
+}
 
-            TileType::MirrorSWNE => match light_dir {
-                NESW::North => vec![NESW::East],
-                NESW::East => vec![NESW::North],
-                NESW::South => vec![NESW::West],
-                NESW::West => vec![NESW::South],
-            },
+// The width of a contraption's grid, or Error if it's empty or its lines aren't all that wide.
+fn grid_width(s: &str) -> Result<usize, Error> {
+    let width = s.lines().next().ok_or(Error)?.chars().count();
+    if width == 0 || s.lines().any(|l| l.chars().count() != width) {
+        return Err(Error);
+    }
+    Ok(width)
+}
 
-            TileType::MirrorNWSE => match light_dir {
-                NESW::North => vec![NESW::West],
-                NESW::East => vec![NESW::South],
-                NESW::South => vec![NESW::East],
-                NESW::West => vec![NESW::North],
-            },
+// The grid on its own, parsed once.  Where Map steps every wavefront in lockstep and keeps a
+// HashSet per tile (which is what the renderers want), this floods from a stack and records
+// the directions light has passed through each tile as a bitmask in one flat Vec.
+struct Contraption {
+    tiles: Vec<TileType>,
+    elements: ElementSet,
+    width: usize,
+    height: usize,
+}
 
-            TileType::SplitVert => match light_dir {
-                NESW::North => vec![NESW::North],
-                NESW::East => vec![NESW::North, NESW::South],
-                NESW::South => vec![NESW::South],
-                NESW::West => vec![NESW::North, NESW::South],
-            },
+impl Contraption {
+    // Error if a character isn't one of the elements.
+    fn with_elements(s: &str, elements: ElementSet) -> Result<Self, Error> {
+        let width = grid_width(s)?;
+        let height = s.lines().count();
+        let tiles = s.lines().flat_map(|l| l.chars().map(|ch| elements.tile_type(ch))).collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { width, height, tiles, elements })
+    }
 
-            TileType::SplitHoriz => match light_dir {
-                NESW::North => vec![NESW::East, NESW::West],
-                NESW::East => vec![NESW::East],
-                NESW::South => vec![NESW::East, NESW::West],
-                NESW::West => vec![NESW::West],
-            },
+    // Index of the next tile along from `index` in direction `dir`, if that's on the grid.
+    fn step(&self, index: usize, dir: NESW) -> Option<usize> {
+        match dir {
+            NESW::North => index.checked_sub(self.width),
+            NESW::East => Some(index + 1).filter(|i| !i.is_multiple_of(self.width)),
+            NESW::South => Some(index + self.width).filter(|i| *i < self.tiles.len()),
+            NESW::West => Some(index).filter(|i| !i.is_multiple_of(self.width)).map(|i| i - 1),
+        }
+    }
+
+    fn energized_mask(&self, start: (usize, usize, NESW)) -> Vec<u8> {
+        let mut mask = vec![0u8; self.tiles.len()];
+        let mut stack = vec![(start.0 * self.width + start.1, start.2)];
+        while let Some((index, dir)) = stack.pop() {
+            if mask[index] & dir.bit() != 0 {
+                continue;
+            }
+            mask[index] |= dir.bit();
+            for &out_dir in self.elements.outputs(self.tiles[index], &dir) {
+                if let Some(next) = self.step(index, out_dir) {
+                    stack.push((next, out_dir));
+                }
+            }
         }
+        mask
+    }
+
+    fn energize(&self, start: (usize, usize, NESW)) -> usize {
+        self.energized_mask(start).iter().filter(|m| **m != 0).count()
     }
+
+    // The tiles a beam crosses from (index, dir) up to the next tile where it divides (or off the
+    // edge, or into a loop).  The tile where it divides is where the run stops, not part of it.
+    fn run(&self, index: usize, dir: NESW) -> Run {
+        let mut tiles = vec![];
+        let mut seen = HashSet::new();
+        let mut state = Some((index, dir));
+        while let Some((index, dir)) = state {
+            let outputs = self.elements.outputs(self.tiles[index], &dir);
+            if outputs.len() > 1 {
+                return Run { tiles, divides_at: Some((index, dir)) };
+            }
+            if !seen.insert((index, dir)) {
+                break;
+            }
+            tiles.push(index);
+            state = outputs.first().and_then(|out| Some((self.step(index, *out)?, *out)));
+        }
+        Run { tiles, divides_at: None }
+    }
+
+    // Part 2: the best of every entry point, sharing the work past each splitter between them.
+    fn max_energized(&self) -> usize {
+        let beams = BeamGraph::new(self);
+        self.edge_starts()
+            .par_iter()
+            .map(|start| beams.energize(self, *start))
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Every way into the grid from outside it.
+    fn edge_starts(&self) -> Vec<(usize, usize, NESW)> {
+        let mut start_points: Vec<(usize, usize, NESW)> = vec![];
+        start_points.extend((0..self.height).map(|r| (r, 0usize, NESW::East)));
+        start_points.extend((0..self.height).map(|r| (r, self.width - 1, NESW::West)));
+        start_points.extend((0..self.width).map(|c| (0usize, c, NESW::South)));
+        start_points.extend((0..self.width).map(|c| (self.height - 1, c, NESW::North)));
+        start_points
+    }
+}
+
+struct Run {
+    tiles: Vec<usize>,
+    divides_at: Option<(usize, NESW)>,
+}
+
+// What a beam energizes once it reaches a splitter doesn't depend on how it got there, so
+// each (splitter, direction) that divides the beam is a node, joined to the nodes its two
+// halves run into next.  Splitters feed each other in loops, so the tiles are gathered per
+// strongly connected component, with every component's set including those downstream of it.
+struct BeamGraph {
+    node_ids: HashMap<(usize, NESW), usize>,
+    component: Vec<usize>,     // node -> component
+    energized: Vec<Vec<u64>>,  // component -> bitset of tiles
+}
+
+impl BeamGraph {
+    fn new(contraption: &Contraption) -> Self {
+        let nodes: Vec<(usize, NESW)> = (0..contraption.tiles.len())
+            .flat_map(|i| [NESW::North, NESW::East, NESW::South, NESW::West].map(|d| (i, d)))
+            .filter(|(i, d)| contraption.elements.outputs(contraption.tiles[*i], d).len() > 1)
+            .collect();
+        let node_ids: HashMap<(usize, NESW), usize> = nodes.iter().enumerate().map(|(id, n)| (*n, id)).collect();
+
+        // The tiles each node energizes itself, before reaching the next nodes along.
+        let runs: Vec<(Vec<usize>, Vec<usize>)> = nodes
+            .par_iter()
+            .map(|(index, dir)| {
+                let mut tiles = vec![*index];
+                let mut next = vec![];
+                for &out in contraption.elements.outputs(contraption.tiles[*index], dir) {
+                    let Some(start) = contraption.step(*index, out) else { continue };
+                    let run = contraption.run(start, out);
+                    tiles.extend(run.tiles);
+                    next.extend(run.divides_at.map(|n| node_ids[&n]));
+                }
+                (tiles, next)
+            })
+            .collect();
+
+        let successors: Vec<Vec<usize>> = runs.iter().map(|(_, next)| next.clone()).collect();
+        let components = strongly_connected(&successors);
+        let mut component = vec![0; nodes.len()];
+        for (c, members) in components.iter().enumerate() {
+            for node in members {
+                component[*node] = c;
+            }
+        }
+
+        // Components come out downstream first, so everything a component leads to is done
+        // by the time it's reached.
+        let words = contraption.tiles.len().div_ceil(64);
+        let mut energized: Vec<Vec<u64>> = Vec::with_capacity(components.len());
+        for (c, members) in components.iter().enumerate() {
+            let mut bits = vec![0u64; words];
+            for node in members {
+                for tile in &runs[*node].0 {
+                    bits[tile / 64] |= 1 << (tile % 64);
+                }
+                for next in &successors[*node] {
+                    if component[*next] != c {
+                        bits.iter_mut().zip(&energized[component[*next]]).for_each(|(a, b)| *a |= b);
+                    }
+                }
+            }
+            energized.push(bits);
+        }
+
+        Self { node_ids, component, energized }
+    }
+
+    fn energize(&self, contraption: &Contraption, start: (usize, usize, NESW)) -> usize {
+        let run = contraption.run(start.0 * contraption.width + start.1, start.2);
+        // A run can cross the same tile twice, going different ways.
+        let mut tiles = run.tiles;
+        tiles.sort_unstable();
+        tiles.dedup();
+
+        let Some(node) = run.divides_at else { return tiles.len() };
+        let downstream = &self.energized[self.component[self.node_ids[&node]]];
+        let extra = tiles.iter().filter(|t| downstream[*t / 64] & (1 << (*t % 64)) == 0).count();
+        downstream.iter().map(|w| w.count_ones() as usize).sum::<usize>() + extra
+    }
+}
+
+// Tarjan's algorithm.  Components are returned in reverse topological order: nothing in a
+// component leads to a component listed after it.  The depth-first search keeps its own stack
+// of (node, next successor to look at) rather than recursing, so long chains of splitters
+// can't overflow the thread's stack.
+fn strongly_connected(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = successors.len();
+    let mut index: Vec<Option<usize>> = vec![None; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = vec![];
+    let mut components = vec![];
+    let mut next_index = 0;
+
+    for root in 0..n {
+        if index[root].is_some() {
+            continue;
+        }
+        let mut calls = vec![(root, 0)];
+        while let Some((v, next)) = calls.pop() {
+            if next == 0 {
+                index[v] = Some(next_index);
+                low[v] = next_index;
+                next_index += 1;
+                stack.push(v);
+                on_stack[v] = true;
+            }
+
+            if let Some(&w) = successors[v].get(next) {
+                calls.push((v, next + 1));
+                match index[w] {
+                    None => calls.push((w, 0)),
+                    Some(w_index) if on_stack[w] => low[v] = low[v].min(w_index),
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            // Done with v: pass its low link back to whoever visited it, and if nothing it
+            // leads to reaches back past it, it heads a component.
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if Some(low[v]) == index[v] {
+                let mut component = vec![];
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
 }
 
+// Light entering tile (row, column) travelling in a direction.
+type BeamState = (usize, usize, NESW);
+
 struct Map {
     tiles: Vec<Vec<Tile>>,
+    elements: ElementSet,
     wavefronts: HashSet<(usize, usize, NESW)>,
+    // For every (row, column, direction) light has entered a tile in, the one it came from on
+    // the tick before (None for the start).  Ticks go in step, so this is a shortest path.
+    reached_from: HashMap<BeamState, Option<BeamState>>,
+}
+
+// One tile on a traced path: where it is, which way light was travelling as it arrived and as
+// it left (None at the end), and every way it could have left if the tile divided the beam.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    rc: (usize, usize),
+    arriving: NESW,
+    leaving: Option<NESW>,
+    divided: Vec<NESW>,
 }
 
 impl Map {
-    fn from_str(s: &str, start: (usize, usize, NESW)) -> Self {
-        Self {
+    // Error if a character isn't one of the elements.
+    fn with_elements(s: &str, start: (usize, usize, NESW), elements: ElementSet) -> Result<Self, Error> {
+        grid_width(s)?;
+        Ok(Self {
             tiles: s
                 .lines()
-                .map(|l| l.chars().map(|ch| Tile::from_char(&ch)).collect())
-                .collect(),
+                .map(|l| l.chars().map(|ch| Tile::from_char(&ch, &elements)).collect())
+                .collect::<Result<_, _>>()?,
 
+            elements,
             wavefronts: HashSet::from([start]),
-        }
+            reached_from: HashMap::from([(start, None)]),
+        })
     }
 
     fn to_str(&self) -> String {
         let lines: Vec<String> = self
             .tiles
             .iter()
-            .map(|line| line.iter().map(|t| t.to_char()).collect())
+            .map(|line| line.iter().map(|t| t.to_char(&self.elements)).collect())
             .collect();
 
         lines.join("\n")
@@ -148,24 +528,18 @@ impl Map {
 
     fn advance(&mut self) {
         let mut new_wf = HashSet::new();
-        for (r, c, nesw) in &self.wavefronts {
-            for out_dir in self.tiles[*r][*c].activate(&nesw) {
-                if matches!(out_dir, NESW::North) && *r > 0 {
-                    if !self.tiles[*r - 1][*c].active_inputs.contains(&out_dir) {
-                        new_wf.insert((*r - 1, *c, out_dir));
-                    }
-                } else if matches!(out_dir, NESW::East) && *c < self.tiles[0].len() - 1 {
-                    if !self.tiles[*r][*c + 1].active_inputs.contains(&out_dir) {
-                        new_wf.insert((*r, *c + 1, out_dir));
-                    }
-                } else if matches!(out_dir, NESW::South) && *r < self.tiles.len() - 1 {
-                    if !self.tiles[*r + 1][*c].active_inputs.contains(&out_dir) {
-                        new_wf.insert((*r + 1, *c, out_dir));
-                    }
-                } else if matches!(out_dir, NESW::West) && *c > 0 {
-                    if !self.tiles[*r][*c - 1].active_inputs.contains(&out_dir) {
-                        new_wf.insert((*r, *c - 1, out_dir));
-                    }
+        for &(r, c, nesw) in &self.wavefronts {
+            for &out_dir in self.tiles[r][c].activate(&nesw, &self.elements) {
+                let (nr, nc) = match out_dir {
+                    NESW::North if r > 0 => (r - 1, c),
+                    NESW::East if c < self.tiles[0].len() - 1 => (r, c + 1),
+                    NESW::South if r < self.tiles.len() - 1 => (r + 1, c),
+                    NESW::West if c > 0 => (r, c - 1),
+                    _ => continue,
+                };
+                if !self.tiles[nr][nc].active_inputs.contains(&out_dir) {
+                    new_wf.insert((nr, nc, out_dir));
+                    self.reached_from.entry((nr, nc, out_dir)).or_insert(Some((r, c, nesw)));
                 }
             }
         }
@@ -181,47 +555,646 @@ impl Map {
             .map(|row| row.iter().filter(|t| !t.active_inputs.is_empty()).count())
             .sum()
     }
+
+    // Runs to completion like energize, keeping a Frame from before every tick and one of the
+    // final state.
+    fn record(&mut self) -> Vec<Frame> {
+        let mut frames = vec![self.frame()];
+        while !self.wavefronts.is_empty() {
+            self.advance();
+            frames.push(self.frame());
+        }
+        frames
+    }
+
+    fn frame(&self) -> Frame {
+        Frame {
+            text: self.to_str(),
+            energized: self
+                .tiles
+                .iter()
+                .map(|row| row.iter().map(|t| !t.active_inputs.is_empty()).collect())
+                .collect(),
+            wavefronts: self.wavefronts.iter().map(|(r, c, _)| (*r, *c)).collect(),
+        }
+    }
+
+    // How light first gets to tile (r, c), from the start to it, or None if it never does.
+    // Only covers what's been simulated so far, so run energize first.
+    fn trace(&self, target: (usize, usize)) -> Option<Vec<Step>> {
+        let states = [NESW::North, NESW::East, NESW::South, NESW::West]
+            .iter()
+            .filter_map(|dir| self.path_to((target.0, target.1, *dir)))
+            .min_by_key(|path| path.len())?;
+
+        let steps = states
+            .iter()
+            .enumerate()
+            .map(|(i, &(r, c, arriving))| {
+                let outputs = self.elements.outputs(self.tiles[r][c].tile_type, &arriving);
+                Step {
+                    rc: (r, c),
+                    arriving,
+                    leaving: states.get(i + 1).map(|next| next.2),
+                    divided: if outputs.len() > 1 { outputs.to_vec() } else { vec![] },
+                }
+            })
+            .collect();
+        Some(steps)
+    }
+
+    fn path_to(&self, state: BeamState) -> Option<Vec<BeamState>> {
+        let mut path = vec![state];
+        let mut previous = *self.reached_from.get(&state)?;
+        while let Some(prev) = previous {
+            path.push(prev);
+            previous = self.reached_from[&prev];
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // A line per step of a traced path: the tile, its element, and what the light did there.
+    fn describe_path(&self, path: &[Step]) -> String {
+        let lines: Vec<String> = path
+            .iter()
+            .map(|step| {
+                let (r, c) = step.rc;
+                let ch = self.elements.get(self.tiles[r][c].tile_type).ch;
+                let what = match step.leaving {
+                    None => format!("arrived heading {}", step.arriving.name()),
+                    Some(leaving) if !step.divided.is_empty() => {
+                        let ways: Vec<&str> = step.divided.iter().map(|d| d.name()).collect();
+                        format!("split {}, followed {}", ways.join("/"), leaving.name())
+                    }
+                    Some(leaving) if leaving != step.arriving => format!("turned {}", leaving.name()),
+                    Some(leaving) => format!("heading {}", leaving.name()),
+                };
+                format!("({}, {}) {} {}", r, c, ch, what)
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+
+    // The elements with the traced path on top: an arrow for the way light left each tile on
+    // it, and X where it ends.
+    fn to_path_str(&self, path: &[Step]) -> String {
+        let mut grid: Vec<Vec<char>> = self
+            .tiles
+            .iter()
+            .map(|row| row.iter().map(|t| self.elements.get(t.tile_type).ch).collect())
+            .collect();
+        for step in path {
+            grid[step.rc.0][step.rc.1] = match step.leaving {
+                Some(NESW::North) => '^',
+                Some(NESW::East) => '>',
+                Some(NESW::South) => 'v',
+                Some(NESW::West) => '<',
+                None => 'X',
+            };
+        }
+        let lines: Vec<String> = grid.into_iter().map(|l| l.into_iter().collect()).collect();
+
+        lines.join("\n")
+    }
+
+    // Energized tiles are shaded by how many directions light has crossed them in, with a
+    // chevron for each of those directions.  Elements are drawn with their glyphs.
+    fn to_picture(&self) -> Picture {
+        let width = self.tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut picture = Picture::new(width * TILE_PX, self.tiles.len() * TILE_PX, (32, 32, 32));
+
+        for (r, row) in self.tiles.iter().enumerate() {
+            for (c, tile) in row.iter().enumerate() {
+                let (x, y) = (c * TILE_PX, r * TILE_PX);
+                let (cx, cy) = (x + TILE_PX / 2, y + TILE_PX / 2);
+                let energized = !tile.active_inputs.is_empty();
+                if energized {
+                    let colour = heat_colour(tile.active_inputs.len() as f64 / 4.0);
+                    picture.rect(x, y, TILE_PX, TILE_PX, colour);
+                }
+
+                let element = if energized { (0, 0, 0) } else { (160, 160, 160) };
+                for (from, to) in self.elements.get(tile.tile_type).glyph_lines(x, y, TILE_PX) {
+                    picture.line(from, to, element);
+                }
+
+                let arm = TILE_PX / 4;
+                for dir in &tile.active_inputs {
+                    let (tip, left, right) = match dir {
+                        NESW::North => ((cx, cy - arm), (cx - arm, cy), (cx + arm, cy)),
+                        NESW::East => ((cx + arm, cy), (cx, cy - arm), (cx, cy + arm)),
+                        NESW::South => ((cx, cy + arm), (cx + arm, cy), (cx - arm, cy)),
+                        NESW::West => ((cx - arm, cy), (cx, cy + arm), (cx, cy - arm)),
+                    };
+                    picture.line(left, tip, (255, 255, 255));
+                    picture.line(right, tip, (255, 255, 255));
+                }
+            }
+        }
+
+        picture
+    }
 }
 
-fn day16() {
-    let input = fs::read_to_string("input.txt").unwrap();
-    let mut map = Map::from_str(&input, (0usize, 0usize, NESW::East));
+// The map at one tick of Map::advance: as to_str draws it, which tiles are energized so far,
+// and the tiles light is about to enter.
+struct Frame {
+    text: String,
+    energized: Vec<Vec<bool>>,
+    wavefronts: HashSet<(usize, usize)>,
+}
 
-    let part1 = map.energize();
-    println!("Part 1: {}", part1);
+impl Frame {
+    // to_str's drawing, with energized tiles in yellow and the wavefronts in bold red.
+    fn to_ansi(&self) -> String {
+        let lines: Vec<String> = self
+            .text
+            .lines()
+            .enumerate()
+            .map(|(r, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(c, ch)| {
+                        if self.wavefronts.contains(&(r, c)) {
+                            format!("\x1b[1;31m{}\x1b[0m", ch)
+                        } else if self.energized[r][c] {
+                            format!("\x1b[33m{}\x1b[0m", ch)
+                        } else {
+                            ch.to_string()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+}
+
+// Plays the frames in the terminal, redrawing in place every `delay`.
+fn play(frames: &[Frame], delay: Duration) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    for (i, frame) in frames.iter().enumerate() {
+        write!(stdout, "\x1b[H\x1b[2J{}\nTick {}/{}\n", frame.to_ansi(), i, frames.len() - 1)?;
+        stdout.flush()?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+// Writes each frame's text to frame_0000.txt, frame_0001.txt, ... in `dir`.
+fn save_frames(frames: &[Frame], dir: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (i, frame) in frames.iter().enumerate() {
+        fs::write(Path::new(dir).join(format!("frame_{:04}.txt", i)), &frame.text)?;
+    }
+    Ok(())
+}
 
-    let mut start_points: Vec<(usize, usize, NESW)> = vec![];
-    start_points.append(
-        &mut (0..map.tiles.len())
-            .map(|r| (r, 0usize, NESW::East))
-            .collect(),
-    );
-    start_points.append(
-        &mut (0..map.tiles.len())
-            .map(|r| (r, map.tiles[0].len() - 1, NESW::West))
-            .collect(),
-    );
-    start_points.append(
-        &mut (0..map.tiles[0].len())
-            .map(|c| (0usize, c, NESW::South))
-            .collect(),
-    );
-    start_points.append(
-        &mut (0..map.tiles[0].len())
-            .map(|c| (map.tiles.len() - 1, c, NESW::North))
-            .collect(),
-    );
-
-    let part2 = start_points
+// An animated GIF of the frames recorded from `map`, `delay` apart and looping forever.
+// Elements are drawn with their glyphs, and the wavefronts fill their tiles.
+fn frames_to_gif(map: &Map, frames: &[Frame], delay: Duration) -> Vec<u8> {
+    const DARK: u8 = 0;
+    const ELEMENT: u8 = 1;
+    const ENERGIZED: u8 = 2;
+    const ENERGIZED_ELEMENT: u8 = 3;
+    const WAVEFRONT: u8 = 4;
+    const PALETTE: [Colour; 5] = [(32, 32, 32), (160, 160, 160), (254, 224, 139), (0, 0, 0), (255, 255, 255)];
+
+    let rows = frames.first().map_or(0, |f| f.energized.len());
+    let cols = frames.first().and_then(|f| f.energized.first()).map_or(0, |row| row.len());
+    let (width, height) = (cols * GIF_TILE_PX, rows * GIF_TILE_PX);
+    let palette: Vec<u8> = PALETTE.iter().flat_map(|c| [c.0, c.1, c.2]).collect();
+
+    // Which pixels of a tile each element's glyph covers.
+    let glyphs: Vec<Vec<bool>> = map
+        .elements
+        .elements
         .iter()
-        .map(|start| Map::from_str(&input, *start).energize())
-        .max()
-        .unwrap();
+        .map(|element| {
+            let mut covered = vec![false; GIF_TILE_PX * GIF_TILE_PX];
+            for (from, to) in element.glyph_lines(0, 0, GIF_TILE_PX) {
+                line_pixels(from, to).for_each(|(px, py)| covered[py * GIF_TILE_PX + px] = true);
+            }
+            covered
+        })
+        .collect();
+
+    let mut bytes = vec![];
+    let mut encoder = gif::Encoder::new(&mut bytes, width as u16, height as u16, &palette)
+        .expect("GIF header for an in-memory buffer");
+    encoder.set_repeat(gif::Repeat::Infinite).expect("GIF loop for an in-memory buffer");
+
+    for frame in frames {
+        let mut pixels = vec![DARK; width * height];
+        for (r, row) in map.tiles.iter().enumerate() {
+            for (c, tile) in row.iter().enumerate() {
+                let energized = frame.energized[r][c];
+                let wavefront = frame.wavefronts.contains(&(r, c));
+                let glyph = &glyphs[tile.tile_type.0 as usize];
+                for py in 0..GIF_TILE_PX {
+                    for px in 0..GIF_TILE_PX {
+                        let on_element = glyph[py * GIF_TILE_PX + px];
+                        pixels[(r * GIF_TILE_PX + py) * width + c * GIF_TILE_PX + px] = match (wavefront, energized, on_element) {
+                            (true, _, _) => WAVEFRONT,
+                            (false, true, true) => ENERGIZED_ELEMENT,
+                            (false, true, false) => ENERGIZED,
+                            (false, false, true) => ELEMENT,
+                            (false, false, false) => DARK,
+                        };
+                    }
+                }
+            }
+        }
+
+        let mut gif_frame = gif::Frame::from_indexed_pixels(width as u16, height as u16, pixels, None);
+        gif_frame.delay = (delay.as_millis() / 10) as u16; // GIF delays are in hundredths
+        encoder.write_frame(&gif_frame).expect("GIF frame for an in-memory buffer");
+    }
+
+    drop(encoder);
+    bytes
+}
+
+fn day16() -> io::Result<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // `-- --input FILE` solves a different contraption, and `-- --elements TABLE` adds the
+    // elements in TABLE (laid out like STANDARD_ELEMENTS) to the standard ones.
+    let mut input_path = "input.txt".to_string();
+    let mut elements = ElementSet::standard();
+    while args.len() >= 2 && (args[0] == "--input" || args[0] == "--elements") {
+        let value = args.remove(1);
+        match args.remove(0).as_str() {
+            "--input" => input_path = value,
+            _ => elements
+                .extend(&fs::read_to_string(&value)?)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad element table"))?,
+        }
+    }
+
+    let input = fs::read_to_string(input_path)?;
+    let bad_input = |_| io::Error::new(io::ErrorKind::InvalidData, "input has a character that isn't an element");
+    let contraption = Contraption::with_elements(&input, elements.clone()).map_err(bad_input)?;
+
+    let part1 = contraption.energize((0usize, 0usize, NESW::East));
+    println!("Part 1: {}", part1);
+
+    // Optionally draw the part 1 beams, e.g. `cargo run -- beams.svg`, or animate them with
+    // `-- beams.gif`, `-- --frames DIR` or `-- --play [MS_PER_TICK]`.  `-- --trace ROW COL`
+    // shows how light gets to that tile.
+    if !args.is_empty() {
+        let mut map = Map::with_elements(&input, (0usize, 0usize, NESW::East), elements).map_err(bad_input)?;
+        let delay = Duration::from_millis(50);
+        match args.iter().map(|a| a.as_str()).collect::<Vec<_>>()[..] {
+            ["--trace", r, c] => {
+                let bad = |_| io::Error::new(io::ErrorKind::InvalidInput, "bad tile");
+                let target = (r.parse().map_err(bad)?, c.parse().map_err(bad)?);
+                map.energize();
+                match map.trace(target) {
+                    Some(path) => println!("{}\n\n{}", map.describe_path(&path), map.to_path_str(&path)),
+                    None => println!("No light reaches {:?}", target),
+                }
+            }
+            ["--play"] => play(&map.record(), delay)?,
+            ["--play", ms] => {
+                let ms = ms.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bad delay"))?;
+                play(&map.record(), Duration::from_millis(ms))?
+            }
+            ["--frames", dir] => {
+                save_frames(&map.record(), dir)?;
+                fs::write(Path::new(dir).join("energized.txt"), map.to_energized_str())?;
+            }
+            [path] if path.ends_with(".gif") => {
+                let frames = map.record();
+                fs::write(path, frames_to_gif(&map, &frames, delay))?
+            }
+            [path] => {
+                map.energize();
+                map.to_picture().save(path)?;
+            }
+            _ => {
+                let usage = "usage: day16 [--input FILE] [--elements TABLE] \
+                    [IMAGE.svg | IMAGE.png | ANIMATION.gif | --frames DIR | --play [MS] | --trace ROW COL]";
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, usage));
+            }
+        }
+    }
+
+    let part2 = contraption.max_energized();
     println!("Part 2: {}", part2);
+
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
-    day16();
+    day16()
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part1_tests() {
+        let mut map = Map::with_elements(&fs::read_to_string("test_input.txt").unwrap(), (0, 0, NESW::East), ElementSet::standard()).unwrap();
+        assert_eq!(map.energize(), 46);
+    }
+
+    #[test]
+    fn contraption_matches_map() {
+        // Every entry point on the example, and a spread of them on the real input.
+        for (file, stride) in [("test_input.txt", 1), ("input.txt", 23)] {
+            let input = fs::read_to_string(file).unwrap();
+            let contraption = Contraption::with_elements(&input, ElementSet::standard()).unwrap();
+            for start in contraption.edge_starts().into_iter().step_by(stride) {
+                let mut map = Map::with_elements(&input, start, ElementSet::standard()).unwrap();
+                let expected = map.energize();
+                assert_eq!(contraption.energize(start), expected, "{} from {:?}", file, start);
+
+                // Same directions recorded on every tile, not just the same count.
+                let mask = contraption.energized_mask(start);
+                for (tile, bits) in map.tiles.iter().flatten().zip(mask) {
+                    assert_eq!(tile.active_inputs.iter().map(|d| d.bit()).sum::<u8>(), bits);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contraption_edges() {
+        let contraption = Contraption::with_elements("..\n..\n..", ElementSet::standard()).unwrap();
+        assert_eq!((contraption.width, contraption.height), (2, 3));
+        assert_eq!(contraption.step(0, NESW::North), None);
+        assert_eq!(contraption.step(0, NESW::West), None);
+        assert_eq!(contraption.step(1, NESW::East), None);
+        assert_eq!(contraption.step(1, NESW::West), Some(0));
+        assert_eq!(contraption.step(1, NESW::South), Some(3));
+        assert_eq!(contraption.step(5, NESW::South), None);
+        assert_eq!(contraption.step(2, NESW::East), Some(3));
+        assert_eq!(contraption.edge_starts().len(), 2 * 3 + 2 * 2);
+        assert_eq!(contraption.energize((2, 1, NESW::North)), 3);
+    }
+
+    #[test]
+    fn beam_graph_matches_contraption() {
+        for file in ["test_input.txt", "input.txt"] {
+            let contraption = Contraption::with_elements(&fs::read_to_string(file).unwrap(), ElementSet::standard()).unwrap();
+            let beams = BeamGraph::new(&contraption);
+            for start in contraption.edge_starts() {
+                assert_eq!(beams.energize(&contraption, start), contraption.energize(start), "{} from {:?}", file, start);
+            }
+        }
+
+        let contraption = Contraption::with_elements(&fs::read_to_string("test_input.txt").unwrap(), ElementSet::standard()).unwrap();
+        assert_eq!(contraption.max_energized(), 51);
+    }
+
+    #[test]
+    fn beam_graph_loops() {
+        // Four splitters sending light round to each other.
+        let contraption = Contraption::with_elements(".|.-.\n.....\n.-.|.\n.....", ElementSet::standard()).unwrap();
+        let beams = BeamGraph::new(&contraption);
+        for start in contraption.edge_starts() {
+            assert_eq!(beams.energize(&contraption, start), contraption.energize(start), "{:?}", start);
+        }
+
+        // Both halves out of the splitter go round the mirrors and back through it lengthways,
+        // looping without ever dividing again.
+        let contraption = Contraption::with_elements("/.\\\n\\-/\n...", ElementSet::standard()).unwrap();
+        let beams = BeamGraph::new(&contraption);
+        assert_eq!(contraption.run(5, NESW::East).divides_at, None);
+        assert_eq!(contraption.run(3, NESW::West).divides_at, None);
+        assert_eq!(contraption.energize((2, 1, NESW::North)), 7);
+        assert_eq!(beams.energize(&contraption, (2, 1, NESW::North)), 7);
+    }
+
+    #[test]
+    fn strongly_connected_tests() {
+        // 0 -> 1 <-> 2 -> 3, 4 on its own.
+        let components = strongly_connected(&[vec![1], vec![2], vec![1, 3], vec![], vec![]]);
+        let mut sorted: Vec<Vec<usize>> = components.iter().map(|c| { let mut c = c.clone(); c.sort(); c }).collect();
+        assert_eq!(sorted, vec![vec![3], vec![1, 2], vec![0], vec![4]]);
+        sorted.sort();
+        assert_eq!(sorted, vec![vec![0], vec![1, 2], vec![3], vec![4]]);
+
+        // A cycle through a million nodes is one component, without running out of stack.
+        let n = 1_000_000;
+        let components = strongly_connected(&(0..n).map(|v| vec![(v + 1) % n]).collect::<Vec<_>>());
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].len(), n);
+    }
+
+    // cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_energize() {
+        let input = fs::read_to_string("input.txt").unwrap();
+        let contraption = Contraption::with_elements(&input, ElementSet::standard()).unwrap();
+        let starts = contraption.edge_starts();
+
+        let timer = std::time::Instant::now();
+        let map_best = starts.iter().map(|s| Map::with_elements(&input, *s, ElementSet::standard()).unwrap().energize()).max();
+        let map_time = timer.elapsed();
+
+        let timer = std::time::Instant::now();
+        let contraption_best = starts.iter().map(|s| contraption.energize(*s)).max();
+        let contraption_time = timer.elapsed();
+
+        let timer = std::time::Instant::now();
+        let memoized_best = Some(contraption.max_energized());
+        let memoized_time = timer.elapsed();
+
+        assert_eq!(map_best, contraption_best);
+        assert_eq!(map_best, memoized_best);
+        println!("BeamGraph in parallel {:?}", memoized_time);
+        println!(
+            "{} entry points: Map {:?}, Contraption {:?} ({:.1}x)",
+            starts.len(), map_time, contraption_time,
+            map_time.as_secs_f64() / contraption_time.as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn element_table_tests() {
+        let standard = ElementSet::standard();
+        assert_eq!(standard.elements.len(), 5);
+        assert_eq!(standard.outputs(standard.tile_type('/').unwrap(), &NESW::North), [NESW::East]);
+        assert_eq!(standard.outputs(standard.tile_type('|').unwrap(), &NESW::West), [NESW::North, NESW::South]);
+        assert!(standard.get(standard.tile_type('.').unwrap()).is_transparent());
+        assert!(!standard.get(standard.tile_type('-').unwrap()).is_transparent());
+
+        assert!(ElementSet::from_table("# x x x").is_err());
+        assert!(ElementSet::from_table("## x x x x").is_err());
+        assert!(ElementSet::from_table("# x x Q x").is_err());
+        assert!(standard.tile_type('#').is_err());
+        assert!(Contraption::with_elements(".|\n#.", ElementSet::standard()).is_err());
+        assert!(Map::with_elements(".|\n#.", (0, 0, NESW::East), ElementSet::standard()).is_err());
+
+        // Every line has to be as long as the first.
+        for ragged in ["..\\\n..\n..", "..\n...", "", "\n"] {
+            assert!(Contraption::with_elements(ragged, ElementSet::standard()).is_err(), "{:?}", ragged);
+            assert!(Map::with_elements(ragged, (0, 0, NESW::East), ElementSet::standard()).is_err(), "{:?}", ragged);
+        }
+
+        // Redefining an element replaces it.
+        let mut elements = ElementSet::standard();
+        elements.extend("/ x x x x").unwrap();
+        assert_eq!(elements.elements.len(), 5);
+        assert_eq!(elements.outputs(elements.tile_type('/').unwrap(), &NESW::North), []);
+    }
+
+    #[test]
+    fn extra_elements() {
+        let mut elements = ElementSet::standard();
+        elements.extend(&fs::read_to_string("extra_elements.txt").unwrap()).unwrap();
+
+        // The absorber stops the beam, and the rest of the row stays dark.
+        let contraption = Contraption::with_elements("..#..", elements.clone()).unwrap();
+        assert_eq!(contraption.energize((0, 0, NESW::East)), 3);
+
+        // Four way splitters send light back the way it came, too.
+        let contraption = Contraption::with_elements(".....\n..+..\n.....", elements.clone()).unwrap();
+        assert_eq!(contraption.energize((1, 0, NESW::East)), 7);
+
+        // The one-way mirror turns light heading east, but lets light heading west through.
+        let contraption = Contraption::with_elements("...\n.d.", elements.clone()).unwrap();
+        assert_eq!(contraption.energize((1, 0, NESW::East)), 3);
+        assert_eq!(contraption.energize((1, 2, NESW::West)), 3);
+
+        // Prisms turn the beam whichever way it arrives.
+        let contraption = Contraption::with_elements(".r.\n...\n.l.", elements.clone()).unwrap();
+        assert_eq!(contraption.run(1, NESW::East).tiles, vec![1, 4, 7, 8]);
+
+        // Simulator, renderer and the splitter graph all pick the new elements up.
+        let input = ["..|.#..\\..", ".r..-..+..", "#..d../..l", "..+..\\.#..", ".l..|..d.r", "../...-..."].join("\n");
+        let contraption = Contraption::with_elements(&input, elements.clone()).unwrap();
+        let beams = BeamGraph::new(&contraption);
+        for start in contraption.edge_starts() {
+            let mut map = Map::with_elements(&input, start, elements.clone()).unwrap();
+            let expected = map.energize();
+            assert_eq!(contraption.energize(start), expected, "{:?}", start);
+            assert_eq!(beams.energize(&contraption, start), expected, "{:?}", start);
+        }
+        let map = Map::with_elements(&input, (0, 0, NESW::East), elements.clone()).unwrap();
+        assert_eq!(map.to_str(), input);
+
+        assert_eq!(elements.get(elements.tile_type('#').unwrap()).glyph(), vec![((0, 0), (2, 2)), ((0, 2), (2, 0))]);
+        assert_eq!(elements.get(elements.tile_type('+').unwrap()).glyph().len(), 4);
+        assert_eq!(elements.get(elements.tile_type('/').unwrap()).glyph_lines(9, 0, 9), vec![((9, 8), (17, 0))]);
+
+        // Glyphs follow what an element does, not what it's called.
+        let mut renamed = ElementSet::standard();
+        renamed.extend("/ N E S W\nm W S E N\ns EW E EW W\n| N x S x").unwrap();
+        let glyph = |ch: char| renamed.get(renamed.tile_type(ch).unwrap()).glyph();
+        assert_eq!(glyph('/'), vec![]);
+        assert_eq!(glyph('m'), glyph('\\'));
+        assert_eq!(glyph('s'), glyph('-'));
+        assert_eq!(glyph('|'), vec![((1, 1), (1, 0)), ((1, 1), (1, 2))]);
+    }
+
+    #[test]
+    fn frame_tests() {
+        let input = fs::read_to_string("test_input.txt").unwrap();
+        let mut map = Map::with_elements(&input, (0, 0, NESW::East), ElementSet::standard()).unwrap();
+        let frames = map.record();
+
+        assert_eq!(frames[0].text, input.trim_end());
+        assert_eq!(frames[0].wavefronts, HashSet::from([(0, 0)]));
+        assert_eq!(frames[1].text.lines().next().unwrap(), ">|...\\....");
+        assert_eq!(frames[1].wavefronts, HashSet::from([(0, 1)]));
+
+        let last = frames.last().unwrap();
+        assert!(last.wavefronts.is_empty());
+        assert_eq!(last.text, map.to_str());
+        assert_eq!(last.energized.iter().flatten().filter(|e| **e).count(), 46);
+        assert!(frames.windows(2).all(|w| {
+            w[0].energized.iter().flatten().filter(|e| **e).count() <= w[1].energized.iter().flatten().filter(|e| **e).count()
+        }));
+
+        assert_eq!(frames[1].to_ansi().lines().next().unwrap(), "\x1b[33m>\x1b[0m\x1b[1;31m|\x1b[0m...\\....");
+
+        let dir = env::temp_dir().join(format!("day16_frames_{}", std::process::id()));
+        save_frames(&frames, dir.to_str().unwrap()).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), frames.len());
+        assert_eq!(fs::read_to_string(dir.join("frame_0001.txt")).unwrap(), frames[1].text);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let gif = frames_to_gif(&map, &frames, Duration::from_millis(100));
+        let mut decoder = gif::DecodeOptions::new().read_info(&gif[..]).unwrap();
+        assert_eq!((decoder.width() as usize, decoder.height() as usize), (10 * GIF_TILE_PX, 10 * GIF_TILE_PX));
+        let mut count = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 10);
+            count += 1;
+        }
+        assert_eq!(count, frames.len());
+    }
+
+    #[test]
+    fn trace_tests() {
+        let mut map = Map::with_elements(&fs::read_to_string("test_input.txt").unwrap(), (0, 0, NESW::East), ElementSet::standard()).unwrap();
+        map.energize();
+
+        let path = map.trace((2, 1)).unwrap();
+        assert_eq!(path, vec![
+            Step { rc: (0, 0), arriving: NESW::East, leaving: Some(NESW::East), divided: vec![] },
+            Step { rc: (0, 1), arriving: NESW::East, leaving: Some(NESW::South), divided: vec![NESW::North, NESW::South] },
+            Step { rc: (1, 1), arriving: NESW::South, leaving: Some(NESW::South), divided: vec![] },
+            Step { rc: (2, 1), arriving: NESW::South, leaving: None, divided: vec![] },
+        ]);
+        assert_eq!(map.describe_path(&path), "\
+(0, 0) . heading east
+(0, 1) | split north/south, followed south
+(1, 1) . heading south
+(2, 1) . arrived heading south");
+        assert_eq!(map.to_path_str(&path).lines().take(4).collect::<Vec<_>>(), [">v...\\....", "|v-.\\.....", ".X...|-...", "........|."]);
+
+        assert_eq!(map.trace((0, 9)), None);
+
+        // Every energized tile has a path, and each step leads into the next.
+        for r in 0..10 {
+            for c in 0..10 {
+                let path = map.trace((r, c));
+                assert_eq!(path.is_some(), !map.tiles[r][c].active_inputs.is_empty());
+                let Some(path) = path else { continue };
+                assert_eq!((path[0].rc, path.last().unwrap().rc), ((0, 0), (r, c)));
+                for pair in path.windows(2) {
+                    let leaving = pair[0].leaving.unwrap();
+                    assert_eq!(pair[1].arriving, leaving);
+                    let (r0, c0) = pair[0].rc;
+                    let expected = match leaving {
+                        NESW::North => (r0 - 1, c0),
+                        NESW::East => (r0, c0 + 1),
+                        NESW::South => (r0 + 1, c0),
+                        NESW::West => (r0, c0 - 1),
+                    };
+                    assert_eq!(pair[1].rc, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn picture_tests() {
+        let mut map = Map::with_elements(".\\\n..", (0, 0, NESW::East), ElementSet::standard()).unwrap();
+        map.energize();
+        let picture = map.to_picture();
+        assert_eq!((picture.width, picture.height), (2 * TILE_PX, 2 * TILE_PX));
+
+        let pixel = |x, y| picture.pixel(x, y);
+        // Light crosses the top row and turns south down the mirror; the bottom left is dark.
+        assert_eq!(pixel(1, 0), heat_colour(0.25));
+        assert_eq!(pixel(1, TILE_PX + 1), (32, 32, 32));
+        // The mirror is drawn corner to corner, and the beam into (0, 0) points east.
+        assert_eq!(pixel(TILE_PX, 0), (0, 0, 0));
+        assert_eq!(pixel(TILE_PX / 2 + TILE_PX / 4, TILE_PX / 2), (255, 255, 255));
+
+        let svg = picture.to_svg();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 1 + 3);
+        assert_eq!(&picture.to_png()[..8], b"\x89PNG\r\n\x1a\n");
+    }
 }
@@ -0,0 +1,178 @@
+// Pictures for the days that export their maps as images: rectangles and lines in pixel
+// coordinates, written out as SVG or PNG.
+
+use std::{fs, io};
+
+pub type Colour = (u8, u8, u8);
+
+#[derive(Debug, Clone)]
+enum Shape {
+    Rect { x: usize, y: usize, w: usize, h: usize, colour: Colour },
+    Line { from: (usize, usize), to: (usize, usize), colour: Colour },
+}
+
+// A picture made of filled rectangles and one pixel wide lines, in pixel coordinates, which
+// can be written out either as SVG or rasterized into a PNG.
+#[derive(Debug, Clone)]
+pub struct Picture {
+    pub width: usize,
+    pub height: usize,
+    shapes: Vec<Shape>,
+}
+
+impl Picture {
+    pub fn new(width: usize, height: usize, background: Colour) -> Self {
+        let mut picture = Picture { width, height, shapes: vec![] };
+        picture.rect(0, 0, width, height, background);
+        picture
+    }
+
+    pub fn rect(&mut self, x: usize, y: usize, w: usize, h: usize, colour: Colour) {
+        self.shapes.push(Shape::Rect { x, y, w, h, colour });
+    }
+
+    pub fn line(&mut self, from: (usize, usize), to: (usize, usize), colour: Colour) {
+        self.shapes.push(Shape::Line { from, to, colour });
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" shape-rendering=\"crispEdges\">\n",
+            self.width, self.height
+        );
+        for shape in &self.shapes {
+            svg += &match shape {
+                Shape::Rect { x, y, w, h, colour } => format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x, y, w, h, hex(colour)
+                ),
+                // Pixel centres are at +0.5, which keeps a one pixel line on one pixel.
+                Shape::Line { from, to, colour } => format!(
+                    "<line x1=\"{}.5\" y1=\"{}.5\" x2=\"{}.5\" y2=\"{}.5\" stroke=\"{}\" stroke-linecap=\"square\"/>\n",
+                    from.0, from.1, to.0, to.1, hex(colour)
+                ),
+            };
+        }
+        svg + "</svg>\n"
+    }
+
+    pub fn to_rgb(&self) -> Vec<u8> {
+        let mut pixels = vec![0u8; self.width * self.height * 3];
+        let mut set = |x: usize, y: usize, colour: &Colour| {
+            if x < self.width && y < self.height {
+                let i = (y * self.width + x) * 3;
+                pixels[i..i + 3].copy_from_slice(&[colour.0, colour.1, colour.2]);
+            }
+        };
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { x, y, w, h, colour } => {
+                    for py in *y..y + h {
+                        for px in *x..x + w {
+                            set(px, py, colour);
+                        }
+                    }
+                }
+                Shape::Line { from, to, colour } => {
+                    for (px, py) in line_pixels(*from, *to) {
+                        set(px, py, colour);
+                    }
+                }
+            }
+        }
+        pixels
+    }
+
+    // The colour of one pixel as to_rgb would draw it: the last shape covering it, or black if
+    // none does (as for anything outside the picture).
+    pub fn pixel(&self, x: usize, y: usize) -> Colour {
+        if x >= self.width || y >= self.height {
+            return (0, 0, 0);
+        }
+        self.shapes
+            .iter()
+            .rev()
+            .find_map(|shape| match shape {
+                Shape::Rect { x: rx, y: ry, w, h, colour } => {
+                    ((*rx..rx + w).contains(&x) && (*ry..ry + h).contains(&y)).then_some(*colour)
+                }
+                Shape::Line { from, to, colour } => line_pixels(*from, *to).any(|p| p == (x, y)).then_some(*colour),
+            })
+            .unwrap_or((0, 0, 0))
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().expect("PNG header for an in-memory buffer");
+        writer.write_image_data(&self.to_rgb()).expect("PNG data for an in-memory buffer");
+        writer.finish().expect("PNG trailer for an in-memory buffer");
+        bytes
+    }
+
+    // Picks the format from the file extension.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        match path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).as_deref() {
+            Some("svg") => fs::write(path, self.to_svg()),
+            Some("png") => fs::write(path, self.to_png()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "expected a .svg or .png path")),
+        }
+    }
+}
+
+// The pixels on a straight line between two points, ends included.
+pub fn line_pixels(from: (usize, usize), to: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+    let dx = to.0 as isize - from.0 as isize;
+    let dy = to.1 as isize - from.1 as isize;
+    let steps = dx.abs().max(dy.abs()).max(1);
+    (0..=steps).map(move |i| {
+        ((from.0 as isize + dx * i / steps) as usize, (from.1 as isize + dy * i / steps) as usize)
+    })
+}
+
+pub fn hex(colour: &Colour) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.0, colour.1, colour.2)
+}
+
+// Blue through green and yellow to red as t goes from 0 to 1.
+pub fn heat_colour(t: f64) -> Colour {
+    const STOPS: [Colour; 4] = [(49, 54, 149), (26, 152, 80), (254, 224, 139), (215, 48, 39)];
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (scaled as usize).min(STOPS.len() - 2);
+    let f = scaled - i as f64;
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    (mix(STOPS[i].0, STOPS[i + 1].0), mix(STOPS[i].1, STOPS[i + 1].1), mix(STOPS[i].2, STOPS[i + 1].2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picture_tests() {
+        assert_eq!(line_pixels((1, 1), (4, 1)).collect::<Vec<_>>(), [(1, 1), (2, 1), (3, 1), (4, 1)]);
+        assert_eq!(line_pixels((3, 0), (0, 3)).collect::<Vec<_>>(), [(3, 0), (2, 1), (1, 2), (0, 3)]);
+        assert_eq!(line_pixels((2, 2), (2, 2)).collect::<Vec<_>>(), [(2, 2), (2, 2)]);
+
+        let mut picture = Picture::new(4, 3, (255, 255, 255));
+        picture.rect(1, 1, 2, 2, (1, 2, 3));
+        picture.line((0, 0), (3, 0), (0, 0, 0));
+        let pixel = |x, y| picture.pixel(x, y);
+        assert_eq!([pixel(0, 0), pixel(3, 0), pixel(2, 2), pixel(0, 2)], [(0, 0, 0), (0, 0, 0), (1, 2, 3), (255, 255, 255)]);
+        assert_eq!(pixel(4, 0), (0, 0, 0));
+        // pixel agrees with the whole raster everywhere.
+        let rgb = picture.to_rgb();
+        for (i, rgb) in rgb.chunks(3).enumerate() {
+            assert_eq!(pixel(i % picture.width, i / picture.width), (rgb[0], rgb[1], rgb[2]));
+        }
+
+        assert!(picture.to_svg().contains("<rect x=\"1\" y=\"1\" width=\"2\" height=\"2\" fill=\"#010203\"/>"));
+        assert_eq!(&picture.to_png()[..8], b"\x89PNG\r\n\x1a\n");
+        assert!(picture.save("picture.gif").is_err());
+
+        assert_eq!((heat_colour(0.0), heat_colour(1.0)), ((49, 54, 149), (215, 48, 39)));
+        assert_eq!(heat_colour(-1.0), heat_colour(0.0));
+    }
+}